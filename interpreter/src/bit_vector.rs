@@ -0,0 +1,158 @@
+/// struct storing a growable list of bits, packed eight to a byte
+#[derive(Clone, Debug, Default)]
+pub struct BitVector {
+    bytes: Vec<u8>,
+    /// number of bits in the vector
+    length: usize,
+}
+
+impl BitVector {
+    /// returns an empty BitVector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// returns the number of bits in the vector
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// checks whether there are no bits in the vector
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// returns the bit at the index, None if it is past the end
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.length {
+            return None;
+        }
+        Some(self.bytes[index / 8] & (1 << (index % 8)) != 0)
+    }
+
+    /// sets the bit at the index, bits past the end are ignored
+    pub fn set(&mut self, index: usize, bit: bool) {
+        if index >= self.length {
+            return;
+        }
+        if bit {
+            self.bytes[index / 8] |= 1 << (index % 8);
+        } else {
+            self.bytes[index / 8] &= !(1 << (index % 8));
+        }
+    }
+
+    /// makes room for the number of bits without growing the room any further
+    pub fn reserve_exact(&mut self, length: usize) {
+        let bytes = length.div_ceil(8);
+        if bytes > self.bytes.capacity() {
+            self.bytes.reserve_exact(bytes - self.bytes.len());
+        }
+    }
+
+    /// adds a bit to the end
+    pub fn push(&mut self, bit: bool) {
+        if self.length.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        self.length += 1;
+        self.set(self.length - 1, bit);
+    }
+
+    /// adds the number of unset bits to the start, the other bits move back
+    pub fn insert_front(&mut self, count: usize) {
+        let mut bits = BitVector::new();
+        bits.reserve_exact(self.length + count);
+        for _ in 0..count {
+            bits.push(false);
+        }
+        for index in 0..self.length {
+            bits.push(self.get(index) == Some(true));
+        }
+        *self = bits;
+    }
+
+    /// removes the number of bits from the start, the other bits move forward
+    pub fn remove_front(&mut self, count: usize) {
+        let mut bits = BitVector::new();
+        for index in count..self.length {
+            bits.push(self.get(index) == Some(true));
+        }
+        *self = bits;
+    }
+
+    /// shortens the vector to the length, it is left as it is if it is shorter
+    pub fn truncate(&mut self, length: usize) {
+        if length >= self.length {
+            return;
+        }
+        self.length = length;
+        self.bytes.truncate(length.div_ceil(8));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_are_stored() {
+        let mut bits = BitVector::new();
+        assert!(bits.is_empty());
+        for index in 0..20 {
+            bits.push(index % 3 == 0);
+        }
+        assert_eq!(bits.len(), 20);
+        for index in 0..20 {
+            assert_eq!(bits.get(index), Some(index % 3 == 0));
+        }
+        assert_eq!(bits.get(20), None);
+
+        bits.set(1, true);
+        bits.set(0, false);
+        bits.set(20, true);
+        assert_eq!(bits.get(0), Some(false));
+        assert_eq!(bits.get(1), Some(true));
+        assert_eq!(bits.len(), 20);
+    }
+
+    #[test]
+    fn truncated_bits_are_cleared() {
+        let mut bits = BitVector::new();
+        for _ in 0..12 {
+            bits.push(true);
+        }
+        bits.truncate(3);
+        assert_eq!(bits.len(), 3);
+        bits.truncate(5);
+        assert_eq!(bits.len(), 3);
+
+        // bits pushed after truncating do not keep their old values
+        bits.push(false);
+        bits.push(false);
+        assert_eq!(bits.get(3), Some(false));
+        assert_eq!(bits.get(4), Some(false));
+    }
+
+    #[test]
+    fn bits_move_when_the_front_changes() {
+        let mut bits = BitVector::new();
+        for index in 0..10 {
+            bits.push(index % 2 == 0);
+        }
+        bits.insert_front(3);
+        assert_eq!(bits.len(), 13);
+        for index in 0..3 {
+            assert_eq!(bits.get(index), Some(false));
+        }
+        for index in 0..10 {
+            assert_eq!(bits.get(index + 3), Some(index % 2 == 0));
+        }
+
+        bits.remove_front(3);
+        assert_eq!(bits.len(), 10);
+        for index in 0..10 {
+            assert_eq!(bits.get(index), Some(index % 2 == 0));
+        }
+    }
+}
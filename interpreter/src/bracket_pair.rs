@@ -1,4 +1,5 @@
 /// struct representing a matching pair of brackets
+#[derive(Clone, Copy, Debug)]
 pub struct BracketPair {
     first_bracket: usize,
     second_bracket: usize,
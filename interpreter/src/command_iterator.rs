@@ -1,9 +1,5 @@
-// TODO: create command iterator
-// - pub next()
-// - jump_to_index
-// - pub jump_to_matching_brace
 use crate::bracket_pair::BracketPair;
-use crate::enums::{BrainfuckCommand, Command, ShellCommand};
+use crate::enums::{BrainfuckCommand, Command, ProcedureCommand};
 
 /// iterator-esque struct that iterates over the supplied commands
 pub struct CommandInterator {
@@ -11,47 +7,124 @@ pub struct CommandInterator {
     max_len: usize,
     commands: Vec<Command>,
     bracket_pairs: Vec<BracketPair>,
+    /// index every bracket jumps to, by the index of the bracket
+    jump_table: Vec<Option<usize>>,
 }
 
 impl CommandInterator {
     /// returns a new instance of command iterator
     pub fn new(commands: Vec<Command>, bracket_pairs: Vec<BracketPair>) -> Self {
+        let jump_table = Self::build_jump_table(&commands, &bracket_pairs);
         Self {
             index: 0,
             max_len: commands.len(),
             commands,
             bracket_pairs,
+            jump_table,
         }
     }
 
-    /// returns the next element in the command list
-    pub fn next(&self) -> Option<Command> {
-        if self.index < self.max_len {
-            self.index += 1;
-            Some(self.commands[self.index - 1])
-        } else {
-            None
+    /// returns the index every bracket jumps to, so that jumps do not have to
+    /// search the bracket pairs
+    fn build_jump_table(commands: &[Command], bracket_pairs: &[BracketPair]) -> Vec<Option<usize>> {
+        let mut jump_table = vec![None; commands.len()];
+        for pair in bracket_pairs {
+            let first = pair.get_first_bracket_index();
+            let second = pair.get_second_bracket_index();
+            match commands.get(first) {
+                // a procedure definition is skipped as a whole
+                Some(Command::Procedure(ProcedureCommand::StartDefinition)) => {
+                    jump_table[first] = Some(second + 1);
+                }
+                Some(_) => {
+                    jump_table[first] = Some(second);
+                    if let Some(
+                        Command::Brainfuck(BrainfuckCommand::ClosedBracket)
+                        | Command::SecondTape(BrainfuckCommand::ClosedBracket),
+                    ) = commands.get(second)
+                    {
+                        jump_table[second] = Some(first);
+                    }
+                }
+                None => (),
+            }
         }
+        jump_table
     }
 
+    /// adds the commands after the ones there are, the bracket pairs count
+    /// from the first added command, the index stays where it is
+    pub fn append(&mut self, commands: Vec<Command>, bracket_pairs: Vec<BracketPair>) {
+        let offset = self.commands.len();
+        let jump_table = Self::build_jump_table(&commands, &bracket_pairs);
+        self.jump_table.extend(
+            jump_table
+                .into_iter()
+                .map(|index| index.map(|index| index + offset)),
+        );
+        self.bracket_pairs
+            .extend(bracket_pairs.into_iter().map(|pair| {
+                BracketPair::from_indeces((
+                    pair.get_first_bracket_index() + offset,
+                    pair.get_second_bracket_index() + offset,
+                ))
+            }));
+        self.commands.extend(commands);
+        self.max_len = self.commands.len();
+    }
+
+    /// returns whether there are commands left
     pub fn has_next(&self) -> bool {
         self.index < self.max_len
     }
 
-    pub fn jump_to_matching_bracket(&self) -> bool {
-        match self.commands[self.index] {
-            Command::Brainfuck(comm) => match comm {
-                BrainfuckCommand::OpenBracket => {
-                    // TODO: come up with a way to couple the brackets together that
-                    // does not suck
-                }
-                BrainfuckCommand::ClosedBracket => {
-                    // TODO: come up with a way to couple the brackets together that
-                    // does not suck
-                }
-                _ => return false,
-            },
-            _ => return false,
+    /// returns the index of the command that will be returned by next
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// returns all pairs of matching brackets
+    pub fn get_bracket_pairs(&self) -> &[BracketPair] {
+        &self.bracket_pairs
+    }
+
+    /// sets the index of the command that will be returned next
+    pub fn jump_to_index(&mut self, index: usize) {
+        self.index = index.min(self.max_len);
+    }
+
+    /// returns the command at the given index
+    pub fn get_command(&self, index: usize) -> Option<Command> {
+        self.commands.get(index).cloned()
+    }
+
+    /// jumps from the last returned bracket to its matching bracket, so that
+    /// the matching bracket will be returned next, from the start of a
+    /// procedure definition it jumps past its end
+    pub fn jump_to_matching_bracket(&mut self) -> bool {
+        if self.index == 0 {
+            return false;
+        }
+        match self.jump_table[self.index - 1] {
+            Some(index) => {
+                self.index = index;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Iterator for CommandInterator {
+    type Item = Command;
+
+    /// returns the next element in the command list
+    fn next(&mut self) -> Option<Command> {
+        if self.index < self.max_len {
+            self.index += 1;
+            Some(self.commands[self.index - 1].clone())
+        } else {
+            None
         }
     }
 }
@@ -0,0 +1,502 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::enums::{Command, ExecutionStatus};
+use crate::executor::Executor;
+use crate::memory_space::MemorySpace;
+use crate::memory_viewer;
+
+/// help text for the debugger commands
+const HELP: &str = "\
+Commands:
+  b, break <location> [if <condition>]  set a breakpoint, the location is
+                                        a command index or line:column
+  d, delete <number>                    delete a breakpoint
+  watch <cell>                          stop when the cell is written to
+  unwatch <cell>                        remove the watchpoint on the cell
+  i, info                               list all breakpoints and watchpoints
+  s, step                               run the next command
+  c, continue                           run until a breakpoint is reached
+  bs, back                              undo the last command
+  rc, reverse-continue                  undo commands until a breakpoint is
+                                        reached or the history is empty
+  w, where                              show the current location
+  m, memory [range]                     show the cells in the range, like
+                                        '10..20', '10..' or '10', or all
+  h, help                               print this help
+  q, quit                               stop debugging
+Conditions compare 'cell', 'cell[N]' or 'pointer' to a number using
+==, !=, <, <=, > or >=, e.g. 'cell[5] == 0' or 'pointer > 100'";
+
+/// the value a breakpoint condition looks at
+#[derive(Clone, Copy, Debug)]
+enum Operand {
+    /// the cell at the given index
+    Cell(usize),
+    /// the cell the pointer is at
+    CurrentCell,
+    /// the location of the pointer
+    Pointer,
+}
+
+/// the ways a breakpoint condition can compare values
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    LessEqual,
+    GreaterEqual,
+    Less,
+    Greater,
+}
+
+impl Comparison {
+    /// all comparisons with their symbols, longer symbols first so that
+    /// '<=' is not mistaken for '<'
+    const ALL: [(&'static str, Comparison); 6] = [
+        ("==", Comparison::Equal),
+        ("!=", Comparison::NotEqual),
+        ("<=", Comparison::LessEqual),
+        (">=", Comparison::GreaterEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+    ];
+
+    /// returns the symbol of the comparison
+    fn symbol(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .map(|(symbol, _)| *symbol)
+            .unwrap_or("?")
+    }
+
+    /// compares the two values
+    fn compare(self, left: u32, right: u32) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::LessEqual => left <= right,
+            Comparison::GreaterEqual => left >= right,
+            Comparison::Less => left < right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+/// condition that has to be met for a breakpoint to stop execution,
+/// for example "cell[5] == 0" or "pointer > 100"
+#[derive(Clone, Copy, Debug)]
+pub struct Condition {
+    operand: Operand,
+    comparison: Comparison,
+    value: u32,
+}
+
+impl Condition {
+    /// returns an Option of condition that tried to parse the provided string
+    pub fn from_string(string: &str) -> Option<Self> {
+        let (symbol, comparison) = Comparison::ALL
+            .iter()
+            .find(|(symbol, _)| string.contains(symbol))?;
+        let mut parts = string.splitn(2, symbol);
+        let left = parts.next()?.trim();
+        let value = parts.next()?.trim().parse().ok()?;
+
+        let operand = match left {
+            "pointer" | "ptr" => Operand::Pointer,
+            "cell" => Operand::CurrentCell,
+            _ => {
+                let index = left.strip_prefix("cell[")?.strip_suffix(']')?;
+                Operand::Cell(index.trim().parse().ok()?)
+            }
+        };
+
+        Some(Self {
+            operand,
+            comparison: *comparison,
+            value,
+        })
+    }
+
+    /// checks whether the condition is met by the memory space
+    pub fn is_met(&self, memory_space: &MemorySpace) -> bool {
+        let left = match self.operand {
+            Operand::Cell(index) => memory_space.get_value_at(index),
+            Operand::CurrentCell => memory_space.get_value_at(memory_space.get_pointer_location()),
+            Operand::Pointer => memory_space.get_pointer_location() as u32,
+        };
+        self.comparison.compare(left, self.value)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operand {
+            Operand::Cell(index) => write!(f, "cell[{}]", index)?,
+            Operand::CurrentCell => write!(f, "cell")?,
+            Operand::Pointer => write!(f, "pointer")?,
+        };
+        write!(f, " {} {}", self.comparison.symbol(), self.value)
+    }
+}
+
+/// breakpoint set in the debugger, it stops execution before the command
+/// at its index is run
+struct Breakpoint {
+    index: usize,
+    condition: Option<Condition>,
+}
+
+/// interactive debugger that runs a program command by command
+pub struct Debugger {
+    executor: Executor,
+    breakpoints: Vec<Breakpoint>,
+    /// whether the debugger should stop
+    quit: bool,
+}
+
+impl Debugger {
+    /// takes an executor with a loaded program
+    pub fn new(executor: Executor) -> Self {
+        Self {
+            executor,
+            breakpoints: Vec::new(),
+            quit: false,
+        }
+    }
+
+    /// reads and runs debugger commands until the user quits
+    pub fn run(&mut self) {
+        println!("Type 'help' for a list of commands");
+        self.print_location();
+
+        while !self.quit {
+            print!("dbg> ");
+            if io::stdout().flush().is_err() {
+                eprintln!("Error: writing to screen failed");
+                break;
+            }
+
+            let mut input = String::new();
+            match io::stdin().read_line(&mut input) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(_) => {
+                    eprintln!("Error: could not read input");
+                    break;
+                }
+            };
+
+            self.run_command(input.trim());
+        }
+    }
+
+    /// runs a single debugger command
+    fn run_command(&mut self, input: &str) {
+        let mut parts = input.splitn(2, ' ');
+        let command = parts.next().unwrap_or("");
+        let argument = parts.next().unwrap_or("").trim();
+
+        match command {
+            "" => (),
+            "b" | "break" => self.set_breakpoint(argument),
+            "d" | "delete" => match argument.parse::<usize>() {
+                Ok(number) if number >= 1 && number <= self.breakpoints.len() => {
+                    self.breakpoints.remove(number - 1);
+                    println!("Deleted breakpoint {}", number);
+                }
+                _ => eprintln!("Error: no breakpoint '{}'", argument),
+            },
+            "watch" => match argument.parse() {
+                Ok(cell) => {
+                    self.executor.get_memory_space_mut().watch_cell(cell);
+                    println!("Watching cell {}", cell);
+                }
+                Err(_) => eprintln!("Error: invalid cell '{}'", argument),
+            },
+            "unwatch" => match argument.parse() {
+                Ok(cell) if self.executor.get_memory_space_mut().unwatch_cell(cell) => {
+                    println!("Stopped watching cell {}", cell);
+                }
+                _ => eprintln!("Error: cell '{}' is not watched", argument),
+            },
+            "i" | "info" => self.print_breakpoints(),
+            "s" | "step" => {
+                self.step();
+                self.print_location();
+            }
+            "c" | "continue" => {
+                self.continue_execution();
+                self.print_location();
+            }
+            "bs" | "back" => {
+                if !self.executor.step_back() {
+                    println!("No more history");
+                }
+                self.print_location();
+            }
+            "rc" | "reverse-continue" => {
+                self.reverse_continue();
+                self.print_location();
+            }
+            "w" | "where" => self.print_location(),
+            "m" | "memory" => self.print_memory(argument),
+            "h" | "help" => println!("{}", HELP),
+            "q" | "quit" => self.quit = true,
+            _ => eprintln!("Error: unknown command '{}', try 'help'", command),
+        }
+    }
+
+    /// parses the argument of the break command and adds the breakpoint
+    fn set_breakpoint(&mut self, argument: &str) {
+        let mut parts = argument.splitn(2, " if ");
+        let location = parts.next().unwrap_or("").trim();
+
+        let index = match self.find_index(location) {
+            Some(index) => index,
+            None => {
+                eprintln!("Error: no command at '{}'", location);
+                return;
+            }
+        };
+
+        let condition = match parts.next() {
+            Some(condition) => match Condition::from_string(condition) {
+                Some(condition) => Some(condition),
+                None => {
+                    eprintln!("Error: invalid condition '{}'", condition.trim());
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        self.breakpoints.push(Breakpoint { index, condition });
+        println!(
+            "Breakpoint {} at {}",
+            self.breakpoints.len(),
+            self.executor.describe_index(index)
+        );
+    }
+
+    /// turns a location, either a command index or line:column, into a command index
+    /// for line:column the first command at or after that position is used
+    fn find_index(&self, location: &str) -> Option<usize> {
+        match location.split_once(':') {
+            Some((line, column)) => {
+                let position = (line.trim().parse().ok()?, column.trim().parse().ok()?);
+                (0..self.executor.get_command_count()).find(|index| {
+                    self.executor
+                        .get_span(*index)
+                        .map(|span| (span.get_line(), span.get_column()))
+                        >= Some(position)
+                })
+            }
+            None => {
+                let index = location.parse().ok()?;
+                if index < self.executor.get_command_count() {
+                    Some(index)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// runs a single command, returns whether execution can go on
+    fn step(&mut self) -> bool {
+        match self.executor.step() {
+            Ok(ExecutionStatus::Running) => true,
+            Ok(ExecutionStatus::Breakpoint) => {
+                println!("Breakpoint in source reached");
+                false
+            }
+            Ok(ExecutionStatus::Watchpoint {
+                changes,
+                command_index,
+            }) => {
+                println!();
+                for change in changes {
+                    println!(
+                        "Watchpoint: {}",
+                        self.executor.describe_change(change, command_index)
+                    );
+                }
+                false
+            }
+            Ok(ExecutionStatus::Finished) => {
+                println!();
+                println!("Program finished");
+                false
+            }
+            Err(error) => {
+                eprintln!("Error: {}", self.executor.describe_error(error));
+                false
+            }
+        }
+    }
+
+    /// runs commands until a breakpoint is reached or the program ends
+    fn continue_execution(&mut self) {
+        // the first command is always run so that the breakpoint the
+        // debugger is currently stopped at does not stop it again
+        while self.step() {
+            if let Some(number) = self.find_breakpoint() {
+                println!();
+                println!("Breakpoint {} reached", number + 1);
+                return;
+            }
+        }
+    }
+
+    /// undoes commands until a breakpoint is reached or there is no more history
+    fn reverse_continue(&mut self) {
+        while self.executor.step_back() {
+            if let Some(number) = self.find_breakpoint() {
+                println!("Breakpoint {} reached", number + 1);
+                return;
+            }
+            let index = self.executor.get_index();
+            if self.executor.get_command(index) == Some(Command::Breakpoint) {
+                println!("Breakpoint in source reached");
+                return;
+            }
+        }
+        println!("No more history");
+    }
+
+    /// returns the index of a breakpoint that stops at the next command, if any
+    fn find_breakpoint(&self) -> Option<usize> {
+        let index = self.executor.get_index();
+        let memory_space = self.executor.get_memory_space();
+        self.breakpoints.iter().position(|breakpoint| {
+            breakpoint.index == index
+                && breakpoint
+                    .condition
+                    .is_none_or(|condition| condition.is_met(memory_space))
+        })
+    }
+
+    /// prints all breakpoints with their conditions and all watched cells
+    fn print_breakpoints(&self) {
+        if self.breakpoints.is_empty() {
+            println!("No breakpoints");
+        }
+        for (number, breakpoint) in self.breakpoints.iter().enumerate() {
+            match breakpoint.condition {
+                Some(condition) => println!(
+                    "{}: {} if {}",
+                    number + 1,
+                    self.executor.describe_index(breakpoint.index),
+                    condition
+                ),
+                None => println!(
+                    "{}: {}",
+                    number + 1,
+                    self.executor.describe_index(breakpoint.index)
+                ),
+            }
+        }
+
+        let watched_cells = self.executor.get_memory_space().get_watched_cells();
+        if watched_cells.is_empty() {
+            println!("No watchpoints");
+        } else {
+            let cells: Vec<String> = watched_cells.iter().map(|cell| cell.to_string()).collect();
+            println!("Watched cells: {}", cells.join(", "));
+        }
+    }
+
+    /// prints the cells in the range, or all cells if there is no range
+    fn print_memory(&self, argument: &str) {
+        let range = Some(argument).filter(|argument| !argument.is_empty());
+        match memory_viewer::format_tapes(&self.executor.get_tapes(), range) {
+            Ok(view) => println!("{}", view),
+            Err(message) => eprintln!("Error: {}", message),
+        }
+    }
+
+    /// prints the next command and the state of the memory
+    fn print_location(&self) {
+        if self.quit {
+            return;
+        }
+        let index = self.executor.get_index();
+        let memory_space = self.executor.get_memory_space();
+        let pointer = memory_space.get_pointer_location();
+
+        let next = if index < self.executor.get_command_count() {
+            self.executor.describe_index(index)
+        } else {
+            String::from("end of program")
+        };
+        let threads = match self.executor.get_thread_count() {
+            1 => String::new(),
+            count => format!(" | {} threads", count),
+        };
+        println!(
+            "next: {} | pointer {}, cell {}{} | history {}",
+            next,
+            pointer,
+            memory_space.get_value_at(pointer),
+            threads,
+            self.executor.get_history_length()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::MemoryUnitType;
+
+    #[test]
+    fn conditions_are_parsed() {
+        let conditions = [
+            ("cell == 0", "cell == 0"),
+            ("cell[5]!=3", "cell[5] != 3"),
+            ("cell[ 5 ] >= 3", "cell[5] >= 3"),
+            ("ptr > 100", "pointer > 100"),
+            ("pointer<=2", "pointer <= 2"),
+            ("cell < 7", "cell < 7"),
+        ];
+        for (string, expected) in conditions {
+            let condition = Condition::from_string(string).unwrap();
+            assert_eq!(condition.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn invalid_conditions_are_rejected() {
+        let invalid = [
+            "",
+            "cell",
+            "cell = 1",
+            "cell == x",
+            "cell[x] == 1",
+            "tape == 1",
+        ];
+        for string in invalid {
+            assert!(Condition::from_string(string).is_none(), "{}", string);
+        }
+    }
+
+    #[test]
+    fn conditions_are_checked() {
+        let mut memory_space = MemorySpace::new(MemoryUnitType::Int8Bit);
+        memory_space.set_value_at(3, 9);
+        memory_space.set_pointer_location(3);
+
+        let is_met = |string| {
+            Condition::from_string(string)
+                .unwrap()
+                .is_met(&memory_space)
+        };
+        assert!(is_met("cell == 9"));
+        assert!(is_met("cell[0] == 0"));
+        assert!(is_met("pointer >= 3"));
+        assert!(!is_met("cell[3] < 9"));
+        assert!(!is_met("pointer != 3"));
+    }
+}
@@ -1,4 +1,9 @@
+use std::fmt;
+
+use crate::memory_space::CellChange;
+
 /// enum of all 8 brainfuck commands
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum BrainfuckCommand {
     PointerIncrement,
     PointerDecrement,
@@ -10,30 +15,533 @@ pub enum BrainfuckCommand {
     ClosedBracket,
 }
 
-/// enum of all shell commands
+/// enum of commands the optimizer replaces runs of brainfuck commands with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OptimizedCommand {
+    /// adds the amount to the cell at the pointer, replaces runs of '+' and '-'
+    AddData(i32),
+    /// moves the pointer by the amount, replaces runs of '>' or of '<'
+    MovePointer(isize),
+    /// sets the cell at the pointer to 0, replaces '[-]' and '[+]'
+    ClearData,
+}
+
+/// enum of the commands Extended Brainfuck Type I adds, the register they use
+/// is the storage of the memory space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtendedCommand {
+    /// '@' ends the program, the rest of the source is data for the tape
+    EndProgram,
+    /// '$' copies the cell at the pointer into the storage
+    StoreData,
+    /// '!' copies the storage into the cell at the pointer
+    LoadData,
+    /// '}' shifts the bits of the cell right by one
+    ShiftRight,
+    /// '{' shifts the bits of the cell left by one
+    ShiftLeft,
+    /// '~' flips all bits of the cell
+    Not,
+    /// '^' xors the cell with the storage
+    Xor,
+    /// '&' ands the cell with the storage
+    And,
+    /// '|' ors the cell with the storage
+    Or,
+}
+
+/// enum of the commands pbrain adds, procedures are numbered by the value of
+/// the cell at the pointer when they are defined or called
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProcedureCommand {
+    /// '(' starts the definition of a procedure, its body is not run
+    StartDefinition,
+    /// ')' ends the definition of a procedure and returns from it
+    EndDefinition,
+    /// ':' calls the procedure
+    Call,
+}
+
+/// enum of the commands the stack dialect adds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StackCommand {
+    /// '$' pushes the value of the cell at the pointer onto the stack
+    Push,
+    /// '!' pops the value on top of the stack into the cell at the pointer
+    Pop,
+}
+
+/// enum of the commands of the dialects with single bit cells that brainfuck
+/// has no command for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BitCommand {
+    /// '*' of Smallfuck flips the bit at the pointer
+    Flip,
+    /// ',' of Boolfuck reads the next bit of the input, bytes are read with
+    /// their least significant bit first
+    ReadBit,
+    /// ';' of Boolfuck writes the bit at the pointer to the output, every
+    /// eight bits are printed as a byte, least significant bit first
+    WriteBit,
+}
+
+/// enum of the languages the parser understands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialect {
+    /// the 8 brainfuck commands
+    Brainfuck,
+    /// brainfuck and the commands of Extended Brainfuck Type I
+    ExtendedTypeOne,
+    /// brainfuck and the procedures of pbrain
+    Pbrain,
+    /// brainfuck and the 'Y' of Brainfork that starts a new thread
+    Brainfork,
+    /// single bit cells with '+' flipping a bit and ',' and ';' for bit I/O
+    Boolfuck,
+    /// single bit cells on a bounded tape with '*' flipping a bit and no I/O
+    Smallfuck,
+    /// brainfuck and a second tape with its own pointer and commands
+    Doublefuck,
+    /// brainfuck and commands that push cells onto a stack and pop them back
+    Stack,
+}
+
+impl Dialect {
+    /// returns the dialect with the name, as it is shown by Display
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "brainfuck" => Some(Dialect::Brainfuck),
+            "extended" => Some(Dialect::ExtendedTypeOne),
+            "pbrain" => Some(Dialect::Pbrain),
+            "brainfork" => Some(Dialect::Brainfork),
+            "boolfuck" => Some(Dialect::Boolfuck),
+            "smallfuck" => Some(Dialect::Smallfuck),
+            "doublefuck" => Some(Dialect::Doublefuck),
+            "stack" => Some(Dialect::Stack),
+            _ => None,
+        }
+    }
+
+    /// returns the type of cell the dialect needs, None if it works with all
+    pub fn get_memory_type(&self) -> Option<MemoryUnitType> {
+        match self {
+            Dialect::Boolfuck | Dialect::Smallfuck => Some(MemoryUnitType::Bit),
+            _ => None,
+        }
+    }
+
+    /// returns the default length of the tape if the dialect has a bounded
+    /// one, moving off either end of it ends the program
+    pub fn get_tape_length(&self) -> Option<usize> {
+        match self {
+            Dialect::Smallfuck => Some(256),
+            _ => None,
+        }
+    }
+
+    /// returns the character shell commands start with, None if the dialect
+    /// uses ':' as a command so that there are no shell commands
+    pub fn get_shell_prefix(&self) -> Option<char> {
+        match self {
+            Dialect::Pbrain | Dialect::Doublefuck => None,
+            _ => Some(':'),
+        }
+    }
+
+    /// checks whether '!' is no command of the dialect, so that it can
+    /// separate the code from its input
+    pub fn can_split_input(&self) -> bool {
+        !matches!(self, Dialect::ExtendedTypeOne | Dialect::Stack)
+    }
+
+    /// returns the number of tapes the dialect works on
+    pub fn get_tape_count(&self) -> usize {
+        match self {
+            Dialect::Doublefuck => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// enum of all commands that can be typed at the shell prompt
+#[derive(Clone, Debug, PartialEq)]
 pub enum ShellCommand {
+    /// prints the list of shell commands
+    Help,
     ResetValues,
     PrintString,
+    /// prints the cells in the range, or all of them
+    PrintMemory(Option<String>),
+    /// prints the location of the pointer and the value it points at
+    PrintPointer,
+    /// prints the values on the stack
+    PrintStack,
+    /// sets the cell at the index to the value
+    SetCell {
+        index: usize,
+        value: u32,
+    },
+    /// resets the memory with cells of the type
+    SetWidth(MemoryUnitType),
+    /// sets what ',' does at the end of the input, or prints it if None
+    SetEof(Option<EofBehavior>),
+    /// turns printing how long every program took on or off
+    ToggleTime,
+    /// turns optimizing the following programs on or off
+    ToggleOptimization,
+    /// saves the tapes and their pointers to the file
+    SaveState(String),
+    /// restores the tapes and their pointers from a snapshot file, or runs the program
+    /// in the file
+    LoadFile(String),
     QuitProgram,
+    /// a shell command that could not be parsed, holds the error message
+    Invalid(String),
 }
 
 /// enum for different kinds of commands to collect them
+#[derive(Clone, Debug, PartialEq)]
 pub enum Command {
     Brainfuck(BrainfuckCommand),
-    Shell(ShellCommand),
+    Optimized(OptimizedCommand),
+    Extended(ExtendedCommand),
+    Procedure(ProcedureCommand),
+    Bit(BitCommand),
+    Stack(StackCommand),
+    /// a brainfuck command that acts on the second tape, in Doublefuck
+    SecondTape(BrainfuckCommand),
+    /// starts a new thread, in Brainfork
+    Fork,
+    /// pauses execution when running in the debugger, ignored otherwise
+    Breakpoint,
 }
 
-/// Enum for the different type of storage cell sizes
-/// classic size is 8 bits unsigned (unsigned Char), more modern variation is Int (32-bit unsigned)
-#[derive(Clone)]
-pub enum MemoryUnit {
-    Int8Bit(u8),
-    Int32Bit(u32),
+/// enum of what ',' stores when there is no more input
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EofBehavior {
+    /// the cell is set to 0
+    Zero,
+    /// the cell is set to its maximum value, -1 in signed terms
+    MaxValue,
+    /// the cell keeps its value
+    Unchanged,
+}
+
+/// enum of the ways '.' writes the value of a cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputEncoding {
+    /// the lowest 8 bits of the value as a byte
+    Bytes,
+    /// the character with the value as its codepoint, encoded as UTF-8
+    Utf8,
+    /// the value as a decimal number followed by a space
+    Decimal,
+}
+
+/// enum of the ways ',' turns the input into cell values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputMode {
+    /// every byte of the input is a value, characters outside of ASCII take
+    /// up several ','
+    Bytes,
+    /// every character of the UTF-8 input is a value, its codepoint
+    Codepoints,
+}
+
+/// enum of what '.' does with a value that is not a valid character
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InvalidOutput {
+    /// the program stops with an error
+    Error,
+    /// the replacement character U+FFFD is written instead
+    Replace,
+    /// nothing is written
+    Skip,
 }
 
 /// Enum for the different type of storage cell sizes to be set by user
 /// classic size is 8 bits unsigned (unsigned Char), more modern variation is Int (32-bit unsigned)
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MemoryUnitType {
+    /// a single bit, used by Boolfuck and Smallfuck
+    Bit,
     Int8Bit,
     Int32Bit,
 }
+
+impl MemoryUnitType {
+    /// returns the number of bits a cell of this type takes up
+    pub fn get_bits(&self) -> usize {
+        match self {
+            MemoryUnitType::Bit => 1,
+            MemoryUnitType::Int8Bit => 8,
+            MemoryUnitType::Int32Bit => 32,
+        }
+    }
+
+    /// returns the largest value a cell of this type can hold
+    pub fn get_max_value(&self) -> u32 {
+        match self {
+            MemoryUnitType::Bit => 1,
+            MemoryUnitType::Int8Bit => u8::MAX as u32,
+            MemoryUnitType::Int32Bit => u32::MAX,
+        }
+    }
+}
+
+/// the formats a trace can be written in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TraceFormat {
+    /// one line per command: "<index> <line>:<column> <command> <pointer> <value>"
+    Text,
+    /// the magic bytes "BFTRACE1" followed by one record per command, every
+    /// record is the command as a byte and then the index, line, column,
+    /// pointer and value as unsigned LEB128 numbers, optimized commands are
+    /// 'a' or 'm' followed by their zigzag encoded amount, or 'c'
+    Binary,
+}
+
+/// enum of the states the executor can be in after running a command
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExecutionStatus {
+    /// there are more commands to run
+    Running,
+    /// a breakpoint was reached
+    Breakpoint,
+    /// the command at the index wrote to watched cells
+    Watchpoint {
+        changes: Vec<CellChange>,
+        command_index: usize,
+    },
+    /// all commands have been run
+    Finished,
+}
+
+/// enum of all errors that can occur while running a program
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuntimeError {
+    /// writing output to the screen failed
+    OutputFailed,
+    /// reading input from the user failed
+    InputFailed,
+    /// writing to the trace file failed
+    TraceNotWritten,
+    /// the maximum number of commands was run, holds the number of commands run
+    StepLimitReached(u64),
+    /// the program ran longer than allowed, holds the number of commands run
+    TimeoutReached(u64),
+    /// the pointer was moved past the end of the tape, holds the tape length
+    TapeLimitReached(usize),
+    /// a procedure was called that was never defined, holds its number
+    UndefinedProcedure(u32),
+    /// procedures were called inside each other too often, holds the depth
+    CallStackOverflow(usize),
+    /// a value was pushed onto a full stack, holds its size
+    StackOverflow(usize),
+    /// a value was popped from an empty stack
+    StackUnderflow,
+    /// '.' was run on a cell that is not a valid character, holds its value
+    InvalidOutput(u32),
+    /// a thread was started when too many were running, holds the maximum
+    ThreadLimitReached(usize),
+    /// a value of the input is too large for the cells, holds the value
+    InputTooLarge(u32),
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::OutputFailed => write!(f, "writing to screen failed"),
+            RuntimeError::InputFailed => write!(f, "could not read input"),
+            RuntimeError::TraceNotWritten => write!(f, "writing to the trace file failed"),
+            RuntimeError::StepLimitReached(steps) => {
+                write!(f, "step limit reached after {} commands", steps)
+            }
+            RuntimeError::TimeoutReached(steps) => {
+                write!(f, "timeout reached after {} commands", steps)
+            }
+            RuntimeError::TapeLimitReached(length) => {
+                write!(
+                    f,
+                    "pointer moved past the end of the tape of {} cells",
+                    length
+                )
+            }
+            RuntimeError::UndefinedProcedure(number) => {
+                write!(f, "procedure {} is not defined", number)
+            }
+            RuntimeError::CallStackOverflow(depth) => {
+                write!(f, "more than {} procedure calls inside each other", depth)
+            }
+            RuntimeError::StackOverflow(size) => {
+                write!(f, "pushed onto a full stack of {} values", size)
+            }
+            RuntimeError::StackUnderflow => write!(f, "popped from an empty stack"),
+            RuntimeError::InvalidOutput(value) => {
+                write!(f, "cell value {} is not a valid character", value)
+            }
+            RuntimeError::ThreadLimitReached(limit) => {
+                write!(f, "more than {} threads running at once", limit)
+            }
+            RuntimeError::InputTooLarge(value) => {
+                write!(f, "input value {} does not fit into a cell", value)
+            }
+        }
+    }
+}
+
+impl fmt::Display for BrainfuckCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            BrainfuckCommand::PointerIncrement => '>',
+            BrainfuckCommand::PointerDecrement => '<',
+            BrainfuckCommand::DataIncrement => '+',
+            BrainfuckCommand::DataDecrement => '-',
+            BrainfuckCommand::ReturnDataAtPointer => '.',
+            BrainfuckCommand::WriteDataToPointer => ',',
+            BrainfuckCommand::OpenBracket => '[',
+            BrainfuckCommand::ClosedBracket => ']',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+impl fmt::Display for ExtendedCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            ExtendedCommand::EndProgram => '@',
+            ExtendedCommand::StoreData => '$',
+            ExtendedCommand::LoadData => '!',
+            ExtendedCommand::ShiftRight => '}',
+            ExtendedCommand::ShiftLeft => '{',
+            ExtendedCommand::Not => '~',
+            ExtendedCommand::Xor => '^',
+            ExtendedCommand::And => '&',
+            ExtendedCommand::Or => '|',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+impl fmt::Display for StackCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            StackCommand::Push => '$',
+            StackCommand::Pop => '!',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+impl fmt::Display for BitCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            BitCommand::Flip => '*',
+            BitCommand::ReadBit => ',',
+            BitCommand::WriteBit => ';',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+impl fmt::Display for ProcedureCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let c = match self {
+            ProcedureCommand::StartDefinition => '(',
+            ProcedureCommand::EndDefinition => ')',
+            ProcedureCommand::Call => ':',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dialect::Brainfuck => write!(f, "brainfuck"),
+            Dialect::ExtendedTypeOne => write!(f, "extended"),
+            Dialect::Pbrain => write!(f, "pbrain"),
+            Dialect::Brainfork => write!(f, "brainfork"),
+            Dialect::Boolfuck => write!(f, "boolfuck"),
+            Dialect::Smallfuck => write!(f, "smallfuck"),
+            Dialect::Doublefuck => write!(f, "doublefuck"),
+            Dialect::Stack => write!(f, "stack"),
+        }
+    }
+}
+
+impl fmt::Display for OptimizedCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OptimizedCommand::AddData(amount) if *amount < 0 => write!(f, "-{}", -amount),
+            OptimizedCommand::AddData(amount) => write!(f, "+{}", amount),
+            OptimizedCommand::MovePointer(amount) if *amount < 0 => write!(f, "<{}", -amount),
+            OptimizedCommand::MovePointer(amount) => write!(f, ">{}", amount),
+            OptimizedCommand::ClearData => write!(f, "[-]"),
+        }
+    }
+}
+
+impl fmt::Display for ShellCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShellCommand::Help => write!(f, ":help"),
+            ShellCommand::ResetValues => write!(f, ":r"),
+            ShellCommand::PrintString => write!(f, ":p"),
+            ShellCommand::PrintMemory(Some(range)) => write!(f, ":mem {}", range),
+            ShellCommand::PrintMemory(None) => write!(f, ":mem"),
+            ShellCommand::PrintPointer => write!(f, ":ptr"),
+            ShellCommand::PrintStack => write!(f, ":stack"),
+            ShellCommand::SetCell { index, value } => write!(f, ":set {}={}", index, value),
+            ShellCommand::SetWidth(memory_type) => {
+                write!(f, ":width {}", memory_type.get_bits())
+            }
+            ShellCommand::SetEof(Some(eof_behavior)) => write!(f, ":eof {}", eof_behavior),
+            ShellCommand::SetEof(None) => write!(f, ":eof"),
+            ShellCommand::ToggleTime => write!(f, ":time"),
+            ShellCommand::ToggleOptimization => write!(f, ":opt"),
+            ShellCommand::SaveState(path) => write!(f, ":save {}", path),
+            ShellCommand::LoadFile(path) => write!(f, ":load {}", path),
+            ShellCommand::QuitProgram => write!(f, ":q"),
+            ShellCommand::Invalid(_) => write!(f, ":?"),
+        }
+    }
+}
+
+impl fmt::Display for EofBehavior {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EofBehavior::Zero => write!(f, "0"),
+            EofBehavior::MaxValue => write!(f, "-1"),
+            EofBehavior::Unchanged => write!(f, "unchanged"),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Brainfuck(command) => write!(f, "{}", command),
+            Command::Optimized(command) => write!(f, "{}", command),
+            Command::Extended(command) => write!(f, "{}", command),
+            Command::Procedure(command) => write!(f, "{}", command),
+            Command::Bit(command) => write!(f, "{}", command),
+            Command::Stack(command) => write!(f, "{}", command),
+            Command::SecondTape(command) => {
+                let c = match command {
+                    BrainfuckCommand::PointerIncrement => '^',
+                    BrainfuckCommand::PointerDecrement => 'v',
+                    BrainfuckCommand::DataIncrement => '/',
+                    BrainfuckCommand::DataDecrement => '\\',
+                    BrainfuckCommand::ReturnDataAtPointer => ':',
+                    BrainfuckCommand::WriteDataToPointer => ';',
+                    BrainfuckCommand::OpenBracket => '{',
+                    BrainfuckCommand::ClosedBracket => '}',
+                };
+                write!(f, "{}", c)
+            }
+            Command::Fork => write!(f, "Y"),
+            Command::Breakpoint => write!(f, "#"),
+        }
+    }
+}
@@ -0,0 +1,1676 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crate::bracket_pair::BracketPair;
+use crate::command_iterator::CommandInterator;
+use crate::enums::{
+    BitCommand, BrainfuckCommand, Command, EofBehavior, ExecutionStatus, ExtendedCommand,
+    InputMode, InvalidOutput, MemoryUnitType, OptimizedCommand, OutputEncoding, ProcedureCommand,
+    RuntimeError, ShellCommand, StackCommand,
+};
+use crate::memory_space::{CellChange, MemorySpace, UndoEntry};
+use crate::memory_viewer;
+use crate::parser::Parser;
+use crate::profiler::Profiler;
+use crate::shell;
+use crate::span::Span;
+use crate::stack::Stack;
+use crate::tracer::Tracer;
+
+/// maximum number of procedure calls inside each other
+const MAX_CALL_DEPTH: usize = 100_000;
+
+/// where a Brainfork thread that waits for its turn continues
+#[derive(Clone, Copy)]
+struct Thread {
+    /// index of the next command of the thread
+    index: usize,
+    pointer_location: usize,
+}
+
+/// state before a command was run that stepping back restores
+struct HistoryEntry {
+    /// index of the command
+    index: usize,
+    memory: UndoEntry,
+    /// call stack before a procedure call or return
+    call_stack: Option<Vec<usize>>,
+    /// number and previous start of a procedure before it was defined
+    definition: Option<(u32, Option<usize>)>,
+    /// waiting threads, if there are any or the command starts one
+    threads: Option<VecDeque<Thread>>,
+    /// index and previous value of the cell a new thread starts on
+    fork_cell: Option<(usize, u32)>,
+    /// state of the second tape before a command that acts on it
+    second_tape: Option<UndoEntry>,
+    /// length and top value of the stack before a push or pop
+    stack: Option<(usize, Option<u32>)>,
+}
+
+/// struct that runs parsed commands on a memory space
+pub struct Executor {
+    memory_space: MemorySpace,
+    /// tapes besides the first one, with their own pointers
+    other_tapes: Vec<MemorySpace>,
+    /// values the stack dialect pushes cells onto
+    stack: Stack,
+    commands: CommandInterator,
+    /// where every command is in the source
+    spans: Vec<Span>,
+    /// names of all sources that were added, a file id is an index into it
+    sources: Vec<String>,
+    /// state before each of the last run commands
+    history: VecDeque<HistoryEntry>,
+    /// maximum number of commands that can be undone, 0 disables the history
+    history_limit: usize,
+    /// number of commands run since the program was loaded
+    steps: u64,
+    /// maximum number of commands a loaded program can run
+    step_limit: Option<u64>,
+    /// maximum time a loaded program can run for
+    timeout: Option<Duration>,
+    /// when the loaded program ran its first command
+    start_time: Option<Instant>,
+    /// records every command that is run, if tracing is enabled
+    tracer: Option<Tracer>,
+    /// counts how often every command is run, if profiling is enabled
+    profiler: Option<Profiler>,
+    /// everything that was printed since the last time it was shown with ':p'
+    /// kept as bytes, so output in any encoding is shown like it was printed
+    output_bytes: Vec<u8>,
+    /// bytes every loaded program reads instead of stdin, from an input option
+    input_source: Option<Vec<u8>>,
+    /// bytes the loaded program has not read yet, if it does not read stdin
+    input: Option<VecDeque<u8>>,
+    /// how ',' turns the input into cell values
+    input_mode: InputMode,
+    /// values of the input that ',' has not read yet
+    input_queue: VecDeque<u32>,
+    /// bits of the input that the bit commands have not read yet
+    input_bits: VecDeque<bool>,
+    /// bits written by the bit commands that do not make up a byte yet
+    output_bits: Vec<bool>,
+    /// whether moving off either end of the tape ends the program
+    bounded_tape: bool,
+    /// what ',' stores when there is no more input
+    eof_behavior: EofBehavior,
+    /// how '.' writes the value of a cell
+    output_encoding: OutputEncoding,
+    /// what '.' does with values that are not valid characters
+    invalid_output: InvalidOutput,
+    /// whether loaded programs are optimized before they run
+    optimize: bool,
+    /// parser with the settings programs loaded with ':load' are parsed with
+    parser: Parser,
+    /// character shell commands start with, shown in the help
+    shell_prefix: char,
+    /// index of the start of every defined procedure, by its number
+    procedures: HashMap<u32, usize>,
+    /// indices the procedures that are running return to
+    call_stack: Vec<usize>,
+    /// Brainfork threads that wait for their turn, the running one is not in it
+    threads: VecDeque<Thread>,
+    /// number of threads started since the program was loaded
+    spawned_threads: usize,
+    /// maximum number of threads that can run at once
+    thread_limit: usize,
+    /// whether the shell prints how long every program ran
+    show_time: bool,
+}
+
+impl Executor {
+    /// takes the type of memory and returns an Executor without commands
+    pub fn new(memory_type: MemoryUnitType) -> Self {
+        Self {
+            memory_space: MemorySpace::new(memory_type),
+            other_tapes: Vec::new(),
+            stack: Stack::new(),
+            commands: CommandInterator::new(Vec::new(), Vec::new()),
+            spans: Vec::new(),
+            sources: Vec::new(),
+            history: VecDeque::new(),
+            history_limit: 0,
+            steps: 0,
+            step_limit: None,
+            timeout: None,
+            start_time: None,
+            tracer: None,
+            profiler: None,
+            output_bytes: Vec::new(),
+            input_source: None,
+            input: None,
+            input_mode: InputMode::Bytes,
+            input_queue: VecDeque::new(),
+            input_bits: VecDeque::new(),
+            output_bits: Vec::new(),
+            bounded_tape: false,
+            eof_behavior: EofBehavior::Zero,
+            output_encoding: OutputEncoding::Utf8,
+            invalid_output: InvalidOutput::Error,
+            optimize: false,
+            parser: Parser::new(),
+            shell_prefix: ':',
+            procedures: HashMap::new(),
+            call_stack: Vec::new(),
+            threads: VecDeque::new(),
+            spawned_threads: 0,
+            thread_limit: 1024,
+            show_time: false,
+        }
+    }
+
+    /// replaces the commands with the ones from the parser, the memory is kept
+    /// they are optimized first if optimization is turned on
+    pub fn load(&mut self, parser: &Parser) {
+        if self.optimize {
+            let mut parser = parser.clone();
+            parser.optimize();
+            return self.load_commands(&parser);
+        }
+        self.load_commands(parser);
+    }
+
+    /// replaces the commands with the ones from the parser as they are, the
+    /// procedures of the old commands are forgotten
+    fn load_commands(&mut self, parser: &Parser) {
+        self.commands = CommandInterator::new(parser.get_commands(), parser.get_bracket_pairs());
+        self.spans = parser.get_spans();
+        self.procedures.clear();
+        self.prepare_run(parser);
+    }
+
+    /// adds the commands from the parser after the loaded ones and runs them
+    /// next, like load, but procedures defined before can still be called
+    pub fn append(&mut self, parser: &Parser) {
+        if self.optimize {
+            let mut parser = parser.clone();
+            parser.optimize();
+            return self.append_commands(&parser);
+        }
+        self.append_commands(parser);
+    }
+
+    /// adds the commands from the parser as they are and jumps to the first one
+    fn append_commands(&mut self, parser: &Parser) {
+        let start = self.get_command_count();
+        self.commands
+            .append(parser.get_commands(), parser.get_bracket_pairs());
+        self.commands.jump_to_index(start);
+        self.spans.extend(parser.get_spans());
+        self.prepare_run(parser);
+    }
+
+    /// resets everything but the memory and the procedures for running newly
+    /// loaded commands, with the input and data of the parser
+    fn prepare_run(&mut self, parser: &Parser) {
+        self.history.clear();
+        self.call_stack.clear();
+        self.threads.clear();
+        self.spawned_threads = 0;
+        self.input = match parser.get_input() {
+            Some(input) => Some(input.into_bytes().into()),
+            None => self.input_source.clone().map(VecDeque::from),
+        };
+        self.input_queue.clear();
+        self.input_bits.clear();
+        self.output_bits.clear();
+        self.steps = 0;
+        self.start_time = None;
+        if let Some(data) = parser.get_data() {
+            self.load_data(&data);
+        }
+        if self.profiler.is_some() {
+            self.profiler = Some(Profiler::new(self.get_command_count()));
+        }
+    }
+
+    /// remembers the name of a source, like a file path, and returns the file
+    /// id the parser has to use for it
+    pub fn add_source(&mut self, name: &str) -> usize {
+        self.sources.push(name.to_string());
+        self.sources.len() - 1
+    }
+
+    /// returns the name of the source with the file id
+    pub fn get_source_name(&self, file_id: usize) -> Option<&str> {
+        self.sources.get(file_id).map(String::as_str)
+    }
+
+    /// puts the characters of the data on the tape, starting at the first cell
+    /// data that does not fit on the tape is left out
+    fn load_data(&mut self, data: &str) {
+        for (index, c) in data.chars().enumerate() {
+            if self.memory_space.set_value_at(index, c as u32).is_none() {
+                break;
+            }
+        }
+    }
+
+    /// sets the parser whose settings, like the dialect, are used for the
+    /// programs loaded with ':load'
+    pub fn set_parser(&mut self, parser: Parser) {
+        self.parser = parser;
+    }
+
+    /// sets the bytes ',' reads instead of stdin, every loaded program reads
+    /// them from the start, None reads stdin
+    pub fn set_input(&mut self, input: Option<Vec<u8>>) {
+        self.input = input.clone().map(VecDeque::from);
+        self.input_source = input;
+        self.input_queue.clear();
+    }
+
+    /// sets how ',' turns the input into cell values
+    pub fn set_input_mode(&mut self, input_mode: InputMode) {
+        self.input_mode = input_mode;
+    }
+
+    /// sets the character shell commands start with, for the help
+    pub fn set_shell_prefix(&mut self, shell_prefix: char) {
+        self.shell_prefix = shell_prefix;
+    }
+
+    /// sets what ',' stores when there is no more input
+    pub fn set_eof_behavior(&mut self, eof_behavior: EofBehavior) {
+        self.eof_behavior = eof_behavior;
+    }
+
+    /// sets how '.' writes the value of a cell and what it does with values
+    /// that are not valid characters
+    pub fn set_output_encoding(
+        &mut self,
+        output_encoding: OutputEncoding,
+        invalid_output: InvalidOutput,
+    ) {
+        self.output_encoding = output_encoding;
+        self.invalid_output = invalid_output;
+    }
+
+    /// returns what ',' stores when there is no more input
+    pub fn get_eof_behavior(&self) -> EofBehavior {
+        self.eof_behavior
+    }
+
+    /// sets whether programs are optimized when they are loaded
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    /// returns whether programs are optimized when they are loaded
+    pub fn is_optimizing(&self) -> bool {
+        self.optimize
+    }
+
+    /// sets the maximum number of commands a loaded program can run,
+    /// None removes the limit
+    pub fn set_step_limit(&mut self, step_limit: Option<u64>) {
+        self.step_limit = step_limit;
+    }
+
+    /// sets the maximum time a loaded program can run for, counted from its
+    /// first command, None removes the limit
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// sets the number of tapes, the tapes that are added are empty and have
+    /// the cell type and maximum size of the first one
+    pub fn set_tape_count(&mut self, count: usize) {
+        self.other_tapes.truncate(count.saturating_sub(1));
+        while self.other_tapes.len() + 1 < count {
+            let mut tape = MemorySpace::new(self.memory_space.get_memory_type());
+            match self.memory_space.get_max_bytes() {
+                Some(max_bytes) => tape.set_max_bytes(max_bytes),
+                None => tape.set_max_length(self.memory_space.get_max_length()),
+            }
+            self.other_tapes.push(tape);
+        }
+    }
+
+    /// returns all tapes, the first one is the one returned by get_memory_space
+    pub fn get_tapes(&self) -> Vec<&MemorySpace> {
+        std::iter::once(&self.memory_space)
+            .chain(&self.other_tapes)
+            .collect()
+    }
+
+    /// returns the stack the stack dialect pushes cells onto
+    pub fn get_stack(&self) -> &Stack {
+        &self.stack
+    }
+
+    /// returns the stack the stack dialect pushes cells onto, to change it
+    pub fn get_stack_mut(&mut self) -> &mut Stack {
+        &mut self.stack
+    }
+
+    /// sets whether moving off either end of the tape ends the program instead
+    /// of being an error
+    pub fn set_bounded_tape(&mut self, bounded_tape: bool) {
+        self.bounded_tape = bounded_tape;
+    }
+
+    /// sets the maximum number of threads that can run at once
+    pub fn set_thread_limit(&mut self, thread_limit: usize) {
+        self.thread_limit = thread_limit;
+    }
+
+    /// returns the number of threads started since the program was loaded
+    pub fn get_spawned_thread_count(&self) -> usize {
+        self.spawned_threads
+    }
+
+    /// returns the number of threads that are running, the first one included
+    pub fn get_thread_count(&self) -> usize {
+        self.threads.len() + 1
+    }
+
+    /// returns the number of commands run since the program was loaded
+    pub fn get_step_count(&self) -> u64 {
+        self.steps
+    }
+
+    /// counts how often every command of the loaded program is run from now on
+    pub fn enable_profiling(&mut self) {
+        self.profiler = Some(Profiler::new(self.get_command_count()));
+    }
+
+    /// returns the profiler if profiling is enabled
+    pub fn get_profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// sets how many commands can be undone, older ones are forgotten
+    pub fn set_history_limit(&mut self, history_limit: usize) {
+        self.history_limit = history_limit;
+        while self.history.len() > history_limit {
+            self.history.pop_front();
+        }
+    }
+
+    /// records every command that is run from now on with the tracer
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    /// writes everything the tracer still buffers to its file
+    pub fn flush_trace(&mut self) -> Result<(), RuntimeError> {
+        match &mut self.tracer {
+            Some(tracer) => tracer.flush().map_err(|_| RuntimeError::TraceNotWritten),
+            None => Ok(()),
+        }
+    }
+
+    /// returns how many commands can currently be undone
+    pub fn get_history_length(&self) -> usize {
+        self.history.len()
+    }
+
+    /// undoes the last run command, returns false if there is nothing to undo
+    /// output that was already printed and input that was read stay as they are
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop_back() {
+            Some(entry) => {
+                self.memory_space.undo(entry.memory);
+                self.commands.jump_to_index(entry.index);
+                if let Some(call_stack) = entry.call_stack {
+                    self.call_stack = call_stack;
+                }
+                match entry.definition {
+                    Some((number, Some(start))) => {
+                        self.procedures.insert(number, start);
+                    }
+                    Some((number, None)) => {
+                        self.procedures.remove(&number);
+                    }
+                    None => (),
+                }
+                if let Some(threads) = entry.threads {
+                    self.threads = threads;
+                }
+                if let (Some(memory), Some(tape)) =
+                    (entry.second_tape, self.other_tapes.first_mut())
+                {
+                    tape.undo(memory);
+                }
+                if let Some((length, top)) = entry.stack {
+                    self.stack.undo(length, top);
+                }
+                if let Some((index, value)) = entry.fork_cell {
+                    if index < self.memory_space.get_length() {
+                        self.memory_space.set_value_at(index, value);
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// returns the memory space the commands are run on
+    pub fn get_memory_space(&self) -> &MemorySpace {
+        &self.memory_space
+    }
+
+    /// returns the memory space the commands are run on, to change it
+    pub fn get_memory_space_mut(&mut self) -> &mut MemorySpace {
+        &mut self.memory_space
+    }
+
+    /// returns the index of the command that will be run next
+    pub fn get_index(&self) -> usize {
+        self.commands.get_index()
+    }
+
+    /// returns the command at the given index
+    pub fn get_command(&self, index: usize) -> Option<Command> {
+        self.commands.get_command(index)
+    }
+
+    /// returns the number of commands that are loaded
+    pub fn get_command_count(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// returns all pairs of matching brackets of the loaded program
+    pub fn get_bracket_pairs(&self) -> &[BracketPair] {
+        self.commands.get_bracket_pairs()
+    }
+
+    /// returns where the command at the index is in the source
+    pub fn get_span(&self, index: usize) -> Option<Span> {
+        self.spans.get(index).copied()
+    }
+
+    /// returns a description of the command at the index and where it is in the source
+    pub fn describe_index(&self, index: usize) -> String {
+        match (self.get_command(index), self.get_span(index)) {
+            (Some(command), Some(span)) => match self.get_source_name(span.get_file_id()) {
+                Some(name) => format!("'{}' (command {}, {}:{})", command, index, name, span),
+                None => format!(
+                    "'{}' (command {}, line {}, column {})",
+                    command,
+                    index,
+                    span.get_line(),
+                    span.get_column()
+                ),
+            },
+            _ => format!("command {}", index),
+        }
+    }
+
+    /// returns a description of the error and of the command that caused it
+    pub fn describe_error(&self, error: RuntimeError) -> String {
+        match error {
+            // the trace is written after the command has run
+            RuntimeError::TraceNotWritten => error.to_string(),
+            _ => format!("{} at {}", error, self.describe_index(self.get_index())),
+        }
+    }
+
+    /// returns a description of a write to a watched cell
+    pub fn describe_change(&self, change: CellChange, command_index: usize) -> String {
+        format!(
+            "cell {} changed from {} to {} by {}",
+            change.index,
+            change.old_value,
+            change.new_value,
+            self.describe_index(command_index)
+        )
+    }
+
+    /// runs commands until all of them are done, breakpoints are ignored and
+    /// writes to watched cells are logged
+    pub fn run(&mut self) -> Result<ExecutionStatus, RuntimeError> {
+        loop {
+            match self.step()? {
+                ExecutionStatus::Running | ExecutionStatus::Breakpoint => (),
+                ExecutionStatus::Watchpoint {
+                    changes,
+                    command_index,
+                } => {
+                    for change in changes {
+                        eprintln!(
+                            "Watchpoint: {}",
+                            self.describe_change(change, command_index)
+                        );
+                    }
+                }
+                status => return Ok(status),
+            }
+        }
+    }
+
+    /// runs the next command and returns the state after it
+    pub fn step(&mut self) -> Result<ExecutionStatus, RuntimeError> {
+        let recorded = self.history_limit > 0 && self.commands.has_next();
+        if recorded {
+            if self.history.len() >= self.history_limit {
+                self.history.pop_front();
+            }
+            let entry = self.get_history_entry();
+            self.history.push_back(entry);
+        }
+
+        let command = match self.commands.next() {
+            Some(command) => command,
+            None => {
+                self.flush_output_bits()?;
+                return Ok(ExecutionStatus::Finished);
+            }
+        };
+
+        let command_index = self.get_index() - 1;
+        let result = match &command {
+            Command::Brainfuck(command) => self.run_brainfuck_command(*command),
+            Command::Optimized(command) => self.run_optimized_command(*command),
+            Command::Extended(command) => self.run_extended_command(*command),
+            Command::Procedure(command) => self.run_procedure_command(*command),
+            Command::Bit(command) => self.run_bit_command(*command),
+            Command::Stack(command) => self.run_stack_command(*command),
+            Command::SecondTape(command) => self.run_second_tape_command(*command),
+            Command::Fork => self.fork_thread(),
+            Command::Breakpoint => return Ok(ExecutionStatus::Breakpoint),
+        };
+        let status = match result {
+            Ok(status) => status,
+            Err(error) => {
+                // the command that failed is the next one again, so that
+                // errors can point at it, and there is nothing to undo
+                self.commands.jump_to_index(command_index);
+                if recorded {
+                    self.history.pop_back();
+                }
+                return Err(error);
+            }
+        };
+        if status == ExecutionStatus::Finished {
+            self.flush_output_bits()?;
+        }
+        self.trace(command_index, &command)?;
+        self.profile(command_index, &command);
+        if !self.threads.is_empty() {
+            self.switch_thread();
+        }
+        let changes = self.memory_space.take_changes();
+        if changes.is_empty() {
+            Ok(status)
+        } else {
+            Ok(ExecutionStatus::Watchpoint {
+                changes,
+                command_index,
+            })
+        }
+    }
+
+    /// lets the next waiting thread run its next command, the running thread
+    /// waits behind the others, threads without commands left are dropped
+    fn switch_thread(&mut self) {
+        self.threads.push_back(Thread {
+            index: self.get_index(),
+            pointer_location: self.memory_space.get_pointer_location(),
+        });
+        let command_count = self.get_command_count();
+        while let Some(thread) = self.threads.pop_front() {
+            if thread.index >= command_count && !self.threads.is_empty() {
+                continue;
+            }
+            self.commands.jump_to_index(thread.index);
+            self.memory_space
+                .set_pointer_location(thread.pointer_location);
+            break;
+        }
+    }
+
+    /// returns what is needed to undo the next command, the procedure state is
+    /// only kept for the procedure commands that change it and the threads
+    /// only while there is more than one
+    fn get_history_entry(&self) -> HistoryEntry {
+        let index = self.get_index();
+        let number = self.get_current_value();
+        let (call_stack, definition) = match self.get_command(index) {
+            Some(Command::Procedure(ProcedureCommand::StartDefinition)) => {
+                (None, Some((number, self.procedures.get(&number).copied())))
+            }
+            Some(Command::Procedure(_)) => (Some(self.call_stack.clone()), None),
+            _ => (None, None),
+        };
+        let forks = self.get_command(index) == Some(Command::Fork);
+        let fork_cell = if forks {
+            let child = self.memory_space.get_pointer_location() + 1;
+            Some((child, self.memory_space.get_value_at(child)))
+        } else {
+            None
+        };
+        HistoryEntry {
+            index,
+            memory: self.memory_space.get_undo_entry(),
+            call_stack,
+            definition,
+            threads: (forks || !self.threads.is_empty()).then(|| self.threads.clone()),
+            fork_cell,
+            second_tape: match self.get_command(index) {
+                Some(Command::SecondTape(_)) => {
+                    self.other_tapes.first().map(MemorySpace::get_undo_entry)
+                }
+                _ => None,
+            },
+            stack: match self.get_command(index) {
+                Some(Command::Stack(_)) => Some((self.stack.len(), self.stack.peek())),
+                _ => None,
+            },
+        }
+    }
+
+    /// returns the value of the cell at the pointer
+    fn get_current_value(&self) -> u32 {
+        self.memory_space
+            .get_value_at(self.memory_space.get_pointer_location())
+    }
+
+    /// carries out a shell command, returns whether the shell should quit
+    pub fn run_shell_command(&mut self, command: ShellCommand) -> bool {
+        match command {
+            ShellCommand::Help => println!("{}", shell::format_help(self.shell_prefix)),
+            ShellCommand::ResetValues => {
+                self.memory_space.reset();
+                self.other_tapes.iter_mut().for_each(MemorySpace::reset);
+                self.stack.clear();
+                self.history.clear();
+                // commands are only kept for the procedures they define
+                self.commands = CommandInterator::new(Vec::new(), Vec::new());
+                self.spans.clear();
+                self.procedures.clear();
+                println!("Reset!");
+            }
+            ShellCommand::PrintString => {
+                let mut stdout = io::stdout();
+                let output = std::mem::take(&mut self.output_bytes);
+                if let Err(error) = stdout.write_all(&output).and_then(|_| writeln!(stdout)) {
+                    eprintln!("Error: could not print the output: {}", error);
+                }
+            }
+            ShellCommand::PrintMemory(range) => {
+                match memory_viewer::format_tapes(&self.get_tapes(), range.as_deref()) {
+                    Ok(view) => println!("{}", view),
+                    Err(message) => eprintln!("Error: {}", message),
+                }
+            }
+            ShellCommand::PrintPointer => {
+                let pointer = self.memory_space.get_pointer_location();
+                println!(
+                    "pointer {}, cell value {}, storage {}",
+                    pointer,
+                    self.memory_space.get_value_at(pointer),
+                    self.memory_space.get_storage()
+                );
+                for (number, tape) in self.other_tapes.iter().enumerate() {
+                    let pointer = tape.get_pointer_location();
+                    println!(
+                        "tape {}: pointer {}, cell value {}",
+                        number + 2,
+                        pointer,
+                        tape.get_value_at(pointer)
+                    );
+                }
+            }
+            ShellCommand::PrintStack => {
+                let values: Vec<String> = self
+                    .stack
+                    .get_values()
+                    .iter()
+                    .rev()
+                    .map(|value| value.to_string())
+                    .collect();
+                match self.stack.get_max_length() {
+                    _ if values.is_empty() => println!("the stack is empty"),
+                    Some(max) => {
+                        println!("{} of {} values: {}", values.len(), max, values.join(" "))
+                    }
+                    None => println!("{} values: {}", values.len(), values.join(" ")),
+                }
+            }
+            // only cells that are on the tape can be set, growing it up to any
+            // index could take all memory
+            ShellCommand::SetCell { index, .. } if index >= self.memory_space.get_length() => {
+                eprintln!("Error: cell {} is past the end of the tape", index);
+            }
+            ShellCommand::SetCell { value, .. }
+                if value > self.memory_space.get_memory_type().get_max_value() =>
+            {
+                eprintln!(
+                    "Error: {} does not fit into a cell of {} bits",
+                    value,
+                    self.memory_space.get_memory_type().get_bits()
+                );
+            }
+            ShellCommand::SetCell { index, value } => {
+                self.memory_space.set_value_at(index, value);
+                println!(
+                    "cell {} is {}",
+                    index,
+                    self.memory_space.get_value_at(index)
+                );
+            }
+            ShellCommand::SetWidth(memory_type)
+                if !self
+                    .get_tapes()
+                    .iter()
+                    .all(|tape| tape.fits_memory_type(memory_type)) =>
+            {
+                eprintln!(
+                    "Error: the tape does not fit into its maximum size with {} bit cells",
+                    memory_type.get_bits()
+                );
+            }
+            ShellCommand::SetWidth(memory_type) => {
+                self.memory_space.set_memory_type(memory_type);
+                for tape in &mut self.other_tapes {
+                    tape.set_memory_type(memory_type);
+                }
+                self.history.clear();
+                println!("cells are {} bits wide", memory_type.get_bits());
+            }
+            ShellCommand::SetEof(Some(eof_behavior)) => {
+                self.eof_behavior = eof_behavior;
+                println!("',' stores {} at the end of the input", eof_behavior);
+            }
+            ShellCommand::SetEof(None) => {
+                println!("',' stores {} at the end of the input", self.eof_behavior);
+            }
+            ShellCommand::ToggleTime => {
+                self.show_time = !self.show_time;
+                println!("timing {}", if self.show_time { "on" } else { "off" });
+            }
+            ShellCommand::ToggleOptimization => {
+                self.optimize = !self.optimize;
+                println!("optimization {}", if self.optimize { "on" } else { "off" });
+            }
+            ShellCommand::SaveState(path) => {
+                match fs::write(&path, MemorySpace::to_snapshot(&self.get_tapes())) {
+                    Ok(_) => println!("Saved to '{}'", path),
+                    Err(error) => eprintln!("Error: could not write '{}': {}", path, error),
+                }
+            }
+            ShellCommand::LoadFile(path) => match fs::read_to_string(&path) {
+                Ok(text) => self.load_file(&path, &text),
+                Err(error) => eprintln!("Error: could not read '{}': {}", path, error),
+            },
+            ShellCommand::QuitProgram => {
+                println!("Quitting");
+                return true;
+            }
+            ShellCommand::Invalid(message) => eprintln!("Error: {}", message),
+        };
+        false
+    }
+
+    /// restores the snapshot or loads the program in the text read from the file
+    fn load_file(&mut self, path: &str, text: &str) {
+        if MemorySpace::is_snapshot(text) {
+            let mut tapes: Vec<&mut MemorySpace> = std::iter::once(&mut self.memory_space)
+                .chain(&mut self.other_tapes)
+                .collect();
+            match MemorySpace::load_snapshot(&mut tapes, text) {
+                Ok(_) => {
+                    self.history.clear();
+                    println!("Loaded '{}'", path);
+                }
+                Err(message) => eprintln!("Error: could not load '{}', {}", path, message),
+            }
+            return;
+        }
+
+        let mut parser = self.parser.clone();
+        parser.set_file_id(self.add_source(path));
+        match parser.parse_string(text) {
+            Some(_) => {
+                self.append(&parser);
+                self.run_program();
+            }
+            None => eprintln!("Error: brackets do not match in '{}'", path),
+        }
+    }
+
+    /// runs the loaded program to the end and reports errors, prints how long
+    /// it ran if timing is turned on
+    pub fn run_program(&mut self) {
+        let start_time = Instant::now();
+        match self.run() {
+            Ok(_) => println!(),
+            Err(error) => eprintln!("Error: {}", self.describe_error(error)),
+        }
+
+        if self.spawned_threads > 0 {
+            eprintln!("{} threads spawned", self.spawned_threads);
+        }
+        if self.show_time {
+            println!("{} commands in {:.3?}", self.steps, start_time.elapsed());
+        }
+    }
+
+    /// counts the command that is about to run and checks that it is allowed
+    /// to run, the time is only looked at every few commands because getting
+    /// it is slow compared to running a command
+    fn check_limits(&mut self) -> Result<(), RuntimeError> {
+        if let Some(step_limit) = self.step_limit {
+            if self.steps >= step_limit {
+                return Err(RuntimeError::StepLimitReached(self.steps));
+            }
+        }
+
+        if let Some(timeout) = self.timeout {
+            let start_time = *self.start_time.get_or_insert_with(Instant::now);
+            if self.steps.is_multiple_of(1024) && start_time.elapsed() >= timeout {
+                return Err(RuntimeError::TimeoutReached(self.steps));
+            }
+        }
+
+        self.steps += 1;
+        Ok(())
+    }
+
+    /// records the command with the state after it if tracing is enabled
+    fn trace(&mut self, index: usize, command: &Command) -> Result<(), RuntimeError> {
+        if let Some(tracer) = &mut self.tracer {
+            let pointer = self.memory_space.get_pointer_location();
+            tracer
+                .record(
+                    index,
+                    command,
+                    self.spans.get(index).copied().unwrap_or_default(),
+                    pointer,
+                    self.memory_space.get_value_at(pointer),
+                )
+                .map_err(|_| RuntimeError::TraceNotWritten)?;
+        }
+        Ok(())
+    }
+
+    /// counts the command and loop iteration if profiling is enabled
+    fn profile(&mut self, index: usize, command: &Command) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(index);
+            // the opening bracket is run again after every jump back, so a
+            // non-zero cell there means the loop body runs once more
+            if *command == Command::Brainfuck(BrainfuckCommand::OpenBracket)
+                && !self.memory_space.is_data_at_pointer_zero()
+            {
+                profiler.record_iteration(index);
+            }
+        }
+    }
+
+    /// runs a single brainfuck command on the memory space
+    fn run_brainfuck_command(
+        &mut self,
+        command: BrainfuckCommand,
+    ) -> Result<ExecutionStatus, RuntimeError> {
+        self.check_limits()?;
+        match command {
+            BrainfuckCommand::PointerIncrement => {
+                if self.memory_space.pointer_increment().is_none() {
+                    if self.bounded_tape {
+                        return Ok(self.end_program());
+                    }
+                    let length = self.memory_space.get_max_length().unwrap_or(0);
+                    return Err(RuntimeError::TapeLimitReached(length));
+                }
+            }
+            BrainfuckCommand::PointerDecrement => {
+                if self.memory_space.pointer_decrement().is_none() {
+                    if self.bounded_tape {
+                        return Ok(self.end_program());
+                    }
+                    if self.memory_space.is_growing_left() {
+                        let length = self.memory_space.get_max_length().unwrap_or(0);
+                        return Err(RuntimeError::TapeLimitReached(length));
+                    }
+                    eprintln!("Pointer index can't be negative!");
+                }
+            }
+            BrainfuckCommand::DataIncrement => self.memory_space.data_increment(),
+            BrainfuckCommand::DataDecrement => self.memory_space.data_decrement(),
+            BrainfuckCommand::ReturnDataAtPointer => self.write_output(self.get_current_value())?,
+            BrainfuckCommand::WriteDataToPointer => match self.read_value()? {
+                Some(value) => self.memory_space.set_data(value),
+                None => self.write_eof(),
+            },
+            BrainfuckCommand::OpenBracket => {
+                if self.memory_space.is_data_at_pointer_zero() {
+                    self.commands.jump_to_matching_bracket();
+                }
+            }
+            BrainfuckCommand::ClosedBracket => {
+                if !self.memory_space.is_data_at_pointer_zero() {
+                    self.commands.jump_to_matching_bracket();
+                }
+            }
+        };
+        Ok(ExecutionStatus::Running)
+    }
+
+    /// runs a single optimized command on the memory space
+    fn run_optimized_command(
+        &mut self,
+        command: OptimizedCommand,
+    ) -> Result<ExecutionStatus, RuntimeError> {
+        self.check_limits()?;
+        match command {
+            OptimizedCommand::AddData(amount) => self.memory_space.data_add(amount),
+            OptimizedCommand::MovePointer(amount) if amount >= 0 => {
+                if self
+                    .memory_space
+                    .pointer_increment_by(amount as usize)
+                    .is_none()
+                {
+                    if self.bounded_tape {
+                        return Ok(self.end_program());
+                    }
+                    let length = self.memory_space.get_max_length().unwrap_or(0);
+                    return Err(RuntimeError::TapeLimitReached(length));
+                }
+            }
+            OptimizedCommand::MovePointer(amount) => {
+                if self
+                    .memory_space
+                    .pointer_decrement_by(amount.unsigned_abs())
+                    .is_none()
+                {
+                    if self.bounded_tape {
+                        return Ok(self.end_program());
+                    }
+                    if self.memory_space.is_growing_left() {
+                        let length = self.memory_space.get_max_length().unwrap_or(0);
+                        return Err(RuntimeError::TapeLimitReached(length));
+                    }
+                    // like a run of '<' the pointer stops at the first cell
+                    let pointer = self.memory_space.get_pointer_location();
+                    self.memory_space.pointer_decrement_by(pointer);
+                    eprintln!("Pointer index can't be negative!");
+                }
+            }
+            OptimizedCommand::ClearData => self.memory_space.set_data(0),
+        };
+        Ok(ExecutionStatus::Running)
+    }
+
+    /// runs a single command of Extended Brainfuck Type I on the memory space
+    fn run_extended_command(
+        &mut self,
+        command: ExtendedCommand,
+    ) -> Result<ExecutionStatus, RuntimeError> {
+        self.check_limits()?;
+        let value = self
+            .memory_space
+            .get_value_at(self.memory_space.get_pointer_location());
+        let storage = self.memory_space.get_storage();
+        match command {
+            ExtendedCommand::EndProgram => return Ok(self.end_program()),
+            ExtendedCommand::StoreData => self.memory_space.set_storage(value),
+            ExtendedCommand::LoadData => self.memory_space.set_data(storage),
+            ExtendedCommand::ShiftRight => self.memory_space.set_data(value >> 1),
+            // the bits shifted out of an 8 bit cell are cut off
+            ExtendedCommand::ShiftLeft => self.memory_space.set_data(value << 1),
+            ExtendedCommand::Not => self.memory_space.set_data(!value),
+            ExtendedCommand::Xor => self.memory_space.set_data(value ^ storage),
+            ExtendedCommand::And => self.memory_space.set_data(value & storage),
+            ExtendedCommand::Or => self.memory_space.set_data(value | storage),
+        };
+        Ok(ExecutionStatus::Running)
+    }
+
+    /// runs a single pbrain command, procedures are numbered by the value of
+    /// the cell at the pointer
+    fn run_procedure_command(
+        &mut self,
+        command: ProcedureCommand,
+    ) -> Result<ExecutionStatus, RuntimeError> {
+        self.check_limits()?;
+        let number = self.get_current_value();
+        match command {
+            ProcedureCommand::StartDefinition => {
+                // the body starts after the '(' and is skipped until it is called
+                self.procedures.insert(number, self.get_index());
+                self.commands.jump_to_matching_bracket();
+            }
+            ProcedureCommand::EndDefinition => {
+                if let Some(index) = self.call_stack.pop() {
+                    self.commands.jump_to_index(index);
+                }
+            }
+            ProcedureCommand::Call => {
+                let start = match self.procedures.get(&number) {
+                    Some(start) => *start,
+                    None => return Err(RuntimeError::UndefinedProcedure(number)),
+                };
+                if self.call_stack.len() >= MAX_CALL_DEPTH {
+                    return Err(RuntimeError::CallStackOverflow(MAX_CALL_DEPTH));
+                }
+                self.call_stack.push(self.get_index());
+                self.commands.jump_to_index(start);
+            }
+        };
+        Ok(ExecutionStatus::Running)
+    }
+
+    /// runs a brainfuck command on the second tape, which is added if there is
+    /// only one
+    fn run_second_tape_command(
+        &mut self,
+        command: BrainfuckCommand,
+    ) -> Result<ExecutionStatus, RuntimeError> {
+        if self.other_tapes.is_empty() {
+            self.set_tape_count(2);
+        }
+        std::mem::swap(&mut self.memory_space, &mut self.other_tapes[0]);
+        let result = self.run_brainfuck_command(command);
+        std::mem::swap(&mut self.memory_space, &mut self.other_tapes[0]);
+        result
+    }
+
+    /// runs a command of the stack dialect on the memory space and the stack
+    fn run_stack_command(
+        &mut self,
+        command: StackCommand,
+    ) -> Result<ExecutionStatus, RuntimeError> {
+        self.check_limits()?;
+        match command {
+            StackCommand::Push => {
+                if self.stack.push(self.get_current_value()).is_none() {
+                    let size = self.stack.get_max_length().unwrap_or(0);
+                    return Err(RuntimeError::StackOverflow(size));
+                }
+            }
+            StackCommand::Pop => match self.stack.pop() {
+                Some(value) => self.memory_space.set_data(value),
+                None => return Err(RuntimeError::StackUnderflow),
+            },
+        };
+        Ok(ExecutionStatus::Running)
+    }
+
+    /// runs a command of Boolfuck or Smallfuck on the memory space
+    fn run_bit_command(&mut self, command: BitCommand) -> Result<ExecutionStatus, RuntimeError> {
+        self.check_limits()?;
+        match command {
+            BitCommand::Flip => {
+                let zero = self.memory_space.is_data_at_pointer_zero();
+                self.memory_space.set_data(zero as u32);
+            }
+            BitCommand::ReadBit => {
+                if self.input_bits.is_empty() {
+                    let bytes = match self.input.as_mut().map(VecDeque::pop_front) {
+                        Some(byte) => byte.map(|byte| vec![byte]),
+                        None => Self::read_line("Enter input:")?.map(String::into_bytes),
+                    };
+                    match bytes {
+                        Some(bytes) => self.input_bits.extend(
+                            bytes
+                                .into_iter()
+                                .flat_map(|byte| (0..8).map(move |bit| byte >> bit & 1 == 1)),
+                        ),
+                        None => {
+                            self.write_eof();
+                            return Ok(ExecutionStatus::Running);
+                        }
+                    }
+                }
+                let bit = self.input_bits.pop_front().unwrap_or(false);
+                self.memory_space.set_data(bit as u32);
+            }
+            BitCommand::WriteBit => {
+                let bit = !self.memory_space.is_data_at_pointer_zero();
+                self.output_bits.push(bit);
+                if self.output_bits.len() == 8 {
+                    self.flush_output_bits()?;
+                }
+            }
+        };
+        Ok(ExecutionStatus::Running)
+    }
+
+    /// prints the bits written by ';' as a byte, the missing bits are 0
+    fn flush_output_bits(&mut self) -> Result<(), RuntimeError> {
+        if self.output_bits.is_empty() {
+            return Ok(());
+        }
+        let byte = self
+            .output_bits
+            .drain(..)
+            .enumerate()
+            .fold(0u8, |byte, (index, bit)| byte | (bit as u8) << index);
+        self.print_byte(byte)
+    }
+
+    /// prints the value of a cell in the output encoding
+    fn write_output(&mut self, value: u32) -> Result<(), RuntimeError> {
+        match self.output_encoding {
+            OutputEncoding::Bytes => self.print_byte(value as u8),
+            OutputEncoding::Utf8 => match char::from_u32(value) {
+                Some(c) => self.print_text(c.encode_utf8(&mut [0; 4])),
+                None => match self.invalid_output {
+                    InvalidOutput::Error => Err(RuntimeError::InvalidOutput(value)),
+                    InvalidOutput::Replace => {
+                        self.print_text(char::REPLACEMENT_CHARACTER.encode_utf8(&mut [0; 4]))
+                    }
+                    InvalidOutput::Skip => Ok(()),
+                },
+            },
+            OutputEncoding::Decimal => self.print_text(&format!("{} ", value)),
+        }
+    }
+
+    /// prints the byte as it is, ':p' shows it as the character with its value
+    fn print_byte(&mut self, byte: u8) -> Result<(), RuntimeError> {
+        self.output_bytes.push(byte);
+        let mut stdout = io::stdout();
+        stdout
+            .write_all(&[byte])
+            .and_then(|_| stdout.flush())
+            .map_err(|_| RuntimeError::OutputFailed)
+    }
+
+    /// prints the text and adds it to the output shown by ':p'
+    fn print_text(&mut self, text: &str) -> Result<(), RuntimeError> {
+        self.output_bytes.extend_from_slice(text.as_bytes());
+        print!("{}", text);
+        io::stdout().flush().map_err(|_| RuntimeError::OutputFailed)
+    }
+
+    /// skips the rest of the commands and returns the status of a finished program
+    fn end_program(&mut self) -> ExecutionStatus {
+        self.commands.jump_to_index(self.get_command_count());
+        ExecutionStatus::Finished
+    }
+
+    /// runs a Brainfork 'Y', the cell at the pointer is set to 0 and the new
+    /// thread starts after the 'Y' one cell to the right, which is set to 1
+    fn fork_thread(&mut self) -> Result<ExecutionStatus, RuntimeError> {
+        self.check_limits()?;
+        if self.get_thread_count() >= self.thread_limit {
+            return Err(RuntimeError::ThreadLimitReached(self.thread_limit));
+        }
+        let pointer_location = self.memory_space.get_pointer_location() + 1;
+        if self
+            .memory_space
+            .write_value_at(pointer_location, 1)
+            .is_none()
+        {
+            let length = self.memory_space.get_max_length().unwrap_or(0);
+            return Err(RuntimeError::TapeLimitReached(length));
+        }
+        self.memory_space.set_data(0);
+        self.threads.push_back(Thread {
+            index: self.get_index(),
+            pointer_location,
+        });
+        self.spawned_threads += 1;
+        Ok(ExecutionStatus::Running)
+    }
+
+    /// returns the next value of the input, when there is none left the given
+    /// input or a line of stdin is turned into values in the input mode
+    /// returns None at the end of the input and an error if the value does not
+    /// fit into a cell, instead of cutting it off
+    fn read_value(&mut self) -> Result<Option<u32>, RuntimeError> {
+        if self.input_queue.is_empty() {
+            let bytes: Vec<u8> = match &mut self.input {
+                Some(input) => input.drain(..).collect(),
+                None => Self::read_line("Enter input:")?
+                    .map(String::into_bytes)
+                    .unwrap_or_default(),
+            };
+            match self.input_mode {
+                InputMode::Bytes => self.input_queue.extend(bytes.into_iter().map(u32::from)),
+                // invalid UTF-8 becomes the replacement character
+                InputMode::Codepoints => self
+                    .input_queue
+                    .extend(String::from_utf8_lossy(&bytes).chars().map(u32::from)),
+            }
+        }
+        let max_value = self.memory_space.get_memory_type().get_max_value();
+        match self.input_queue.pop_front() {
+            Some(value) if value > max_value => Err(RuntimeError::InputTooLarge(value)),
+            value => Ok(value),
+        }
+    }
+
+    /// prints the prompt and returns a line read from stdin, None at the end
+    /// of the input, the prompt goes to stderr and only to a terminal so that
+    /// it does not end up in the output of the program
+    fn read_line(prompt: &str) -> Result<Option<String>, RuntimeError> {
+        if io::stdin().is_terminal() {
+            let _ = io::stdout().flush();
+            eprintln!("{}", prompt);
+        }
+        let mut read_input = String::new();
+        match io::stdin().read_line(&mut read_input) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(read_input)),
+            Err(_) => Err(RuntimeError::InputFailed),
+        }
+    }
+
+    /// stores what the eof behavior asks for in the cell at the pointer
+    fn write_eof(&mut self) {
+        match self.eof_behavior {
+            EofBehavior::Zero => self.memory_space.set_data(0),
+            EofBehavior::MaxValue => self.memory_space.set_data(u32::MAX),
+            EofBehavior::Unchanged => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::Dialect;
+
+    /// returns an executor set up for the dialect like the interpreter does,
+    /// without input and with output written as bytes
+    fn executor(dialect: Dialect, memory_type: MemoryUnitType) -> Executor {
+        let mut executor = Executor::new(dialect.get_memory_type().unwrap_or(memory_type));
+        executor.set_input(Some(Vec::new()));
+        executor.set_output_encoding(OutputEncoding::Bytes, InvalidOutput::Error);
+        executor.set_bounded_tape(dialect.get_tape_length().is_some());
+        let memory_space = executor.get_memory_space_mut();
+        memory_space.set_max_length(dialect.get_tape_length());
+        memory_space.set_grows_left(dialect == Dialect::Boolfuck);
+        executor.set_tape_count(dialect.get_tape_count());
+        let mut parser = Parser::new();
+        parser.set_dialect(dialect);
+        executor.set_parser(parser);
+        executor
+    }
+
+    /// parses the program with the parser of the executor and loads it
+    fn load(executor: &mut Executor, program: &str) {
+        let mut parser = executor.parser.clone();
+        parser.parse_string(program).unwrap();
+        executor.load(&parser);
+    }
+
+    #[test]
+    fn watched_writes_are_reported() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int8Bit);
+        executor.get_memory_space_mut().watch_cell(1);
+        load(&mut executor, "+>++<-");
+
+        assert_eq!(executor.step(), Ok(ExecutionStatus::Running));
+        assert_eq!(executor.step(), Ok(ExecutionStatus::Running));
+        assert_eq!(
+            executor.step(),
+            Ok(ExecutionStatus::Watchpoint {
+                changes: vec![CellChange {
+                    index: 1,
+                    old_value: 0,
+                    new_value: 1
+                }],
+                command_index: 2,
+            })
+        );
+        executor.step().unwrap();
+        executor.step().unwrap();
+        assert_eq!(executor.step(), Ok(ExecutionStatus::Running));
+
+        // after unwatching the cell its writes run through
+        executor.get_memory_space_mut().unwatch_cell(1);
+        load(&mut executor, ">+");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        assert_eq!(executor.get_memory_space().get_value_at(1), 3);
+    }
+
+    #[test]
+    fn commands_are_undone() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int8Bit);
+        executor.set_history_limit(10);
+        load(&mut executor, "++>+[-]");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        assert_eq!(executor.get_memory_space().get_value_at(1), 0);
+
+        assert!(executor.step_back());
+        assert!(executor.step_back());
+        assert_eq!(executor.get_index(), 5);
+        assert_eq!(executor.get_memory_space().get_value_at(1), 1);
+        assert!(executor.step_back());
+        assert!(executor.step_back());
+        assert!(executor.step_back());
+        assert_eq!(executor.get_memory_space().get_pointer_location(), 0);
+        assert_eq!(executor.get_memory_space().get_length(), 1);
+        assert!(executor.step_back());
+        assert!(executor.step_back());
+        assert_eq!(executor.get_memory_space().get_value_at(0), 0);
+        assert!(!executor.step_back());
+    }
+
+    #[test]
+    fn failed_commands_are_not_undone() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int8Bit);
+        executor.set_history_limit(10);
+        executor.get_memory_space_mut().set_max_length(Some(1));
+        load(&mut executor, "+>");
+        executor.step().unwrap();
+        assert_eq!(executor.step(), Err(RuntimeError::TapeLimitReached(1)));
+        assert_eq!(executor.get_history_length(), 1);
+
+        // the first step back undoes the '+' before the failed '>'
+        assert!(executor.step_back());
+        assert_eq!(executor.get_index(), 0);
+        assert_eq!(executor.get_memory_space().get_value_at(0), 0);
+    }
+
+    #[test]
+    fn endless_programs_are_stopped() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int8Bit);
+        executor.set_step_limit(Some(100));
+        load(&mut executor, "+[]");
+        assert_eq!(executor.run(), Err(RuntimeError::StepLimitReached(100)));
+        assert_eq!(executor.get_step_count(), 100);
+
+        // every loaded program gets all of its steps
+        load(&mut executor, "[-]");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        assert_eq!(executor.get_step_count(), 3);
+
+        executor.set_step_limit(None);
+        executor.set_timeout(Some(Duration::ZERO));
+        load(&mut executor, "+[]");
+        assert_eq!(executor.run(), Err(RuntimeError::TimeoutReached(0)));
+    }
+
+    #[test]
+    fn tape_caps_are_kept() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int8Bit);
+        executor.get_memory_space_mut().set_max_bytes(4);
+        load(&mut executor, ">>>");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        load(&mut executor, ">");
+        assert_eq!(executor.run(), Err(RuntimeError::TapeLimitReached(4)));
+
+        // four cells do not fit into four bytes with wider cells
+        executor.run_shell_command(ShellCommand::SetWidth(MemoryUnitType::Int32Bit));
+        let memory_space = executor.get_memory_space();
+        assert_eq!(memory_space.get_memory_type(), MemoryUnitType::Int8Bit);
+
+        executor.run_shell_command(ShellCommand::ResetValues);
+        executor.run_shell_command(ShellCommand::SetWidth(MemoryUnitType::Int32Bit));
+        let memory_space = executor.get_memory_space();
+        assert_eq!(memory_space.get_memory_type(), MemoryUnitType::Int32Bit);
+        assert_eq!(memory_space.get_max_length(), Some(1));
+    }
+
+    /// returns a path in the temporary directory for a file of the test
+    fn temp_path(name: &str) -> String {
+        let name = format!("interpreter-{}-{}", std::process::id(), name);
+        std::env::temp_dir()
+            .join(name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn snapshots_restore_every_tape() {
+        let path = temp_path("tapes.snapshot");
+        let mut executor = executor(Dialect::Doublefuck, MemoryUnitType::Int8Bit);
+        load(&mut executor, "++^//");
+        executor.run().unwrap();
+        executor.run_shell_command(ShellCommand::SaveState(path.clone()));
+        executor.run_shell_command(ShellCommand::ResetValues);
+        executor.run_shell_command(ShellCommand::LoadFile(path.clone()));
+        fs::remove_file(&path).unwrap();
+
+        let tapes = executor.get_tapes();
+        assert_eq!(tapes[0].get_value_at(0), 2);
+        assert_eq!(tapes[1].get_pointer_location(), 1);
+        assert_eq!(tapes[1].get_value_at(1), 2);
+    }
+
+    #[test]
+    fn snapshots_have_to_fit_into_the_tape() {
+        let path = temp_path("wide.snapshot");
+        let mut wide = executor(Dialect::Brainfuck, MemoryUnitType::Int32Bit);
+        load(&mut wide, "+>+");
+        wide.run().unwrap();
+        wide.run_shell_command(ShellCommand::SaveState(path.clone()));
+
+        // two cells of 32 bits take up eight bytes
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int8Bit);
+        executor.get_memory_space_mut().set_max_bytes(4);
+        executor.run_shell_command(ShellCommand::LoadFile(path.clone()));
+        let memory_space = executor.get_memory_space();
+        assert_eq!(memory_space.get_memory_type(), MemoryUnitType::Int8Bit);
+        assert_eq!(memory_space.get_max_length(), Some(4));
+
+        executor.get_memory_space_mut().set_max_bytes(8);
+        executor.run_shell_command(ShellCommand::LoadFile(path.clone()));
+        fs::remove_file(&path).unwrap();
+        let memory_space = executor.get_memory_space();
+        assert_eq!(memory_space.get_memory_type(), MemoryUnitType::Int32Bit);
+        assert_eq!(memory_space.get_max_length(), Some(2));
+        assert_eq!(memory_space.get_value_at(1), 1);
+    }
+
+    #[test]
+    fn set_cells_have_to_fit() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int8Bit);
+        let set = |index, value| ShellCommand::SetCell { index, value };
+        executor.run_shell_command(set(0, 255));
+        executor.run_shell_command(set(0, 300));
+        executor.run_shell_command(set(1, 7));
+        let memory_space = executor.get_memory_space();
+        assert_eq!(memory_space.get_value_at(0), 255);
+        assert_eq!(memory_space.get_length(), 1);
+    }
+
+    #[test]
+    fn extended_commands_are_run() {
+        let mut executor = executor(Dialect::ExtendedTypeOne, MemoryUnitType::Int8Bit);
+        // the 'A' after '@' is loaded into the first cell
+        load(&mut executor, "$>!{{>!}|~^&@A");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+
+        let memory_space = executor.get_memory_space();
+        assert_eq!(memory_space.get_storage(), 65);
+        // 65 shifted left twice loses its top bit in an 8 bit cell
+        assert_eq!(memory_space.get_value_at(1), 4);
+        // (65 >> 1 | 65), inverted, xored and anded with 65
+        assert_eq!(memory_space.get_value_at(2), 65);
+        assert_eq!(executor.get_step_count(), 13);
+    }
+
+    #[test]
+    fn procedures_are_called() {
+        let mut executor = executor(Dialect::Pbrain, MemoryUnitType::Int8Bit);
+        load(&mut executor, "+(>++<)::");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        assert_eq!(executor.get_memory_space().get_value_at(1), 4);
+
+        // appended commands can call procedures defined before
+        let mut parser = executor.parser.clone();
+        parser.parse_string(":").unwrap();
+        executor.append(&parser);
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        assert_eq!(executor.get_memory_space().get_value_at(1), 6);
+
+        // loading and resetting forget them
+        load(&mut executor, ":");
+        assert_eq!(executor.run(), Err(RuntimeError::UndefinedProcedure(1)));
+        load(&mut executor, "(>++<)");
+        executor.run().unwrap();
+        executor.run_shell_command(ShellCommand::ResetValues);
+        executor.run_shell_command(ShellCommand::SetCell { index: 0, value: 1 });
+        executor.append(&parser);
+        assert_eq!(executor.run(), Err(RuntimeError::UndefinedProcedure(1)));
+    }
+
+    #[test]
+    fn forked_threads_run_on_their_own_cells() {
+        let mut executor = executor(Dialect::Brainfork, MemoryUnitType::Int8Bit);
+        executor.get_memory_space_mut().watch_cell(1);
+        load(&mut executor, "Y+");
+        assert_eq!(
+            executor.step(),
+            Ok(ExecutionStatus::Watchpoint {
+                changes: vec![CellChange {
+                    index: 1,
+                    old_value: 0,
+                    new_value: 1
+                }],
+                command_index: 0,
+            })
+        );
+        executor.get_memory_space_mut().unwatch_cell(1);
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        assert_eq!(executor.get_spawned_thread_count(), 1);
+
+        // the parent adds to the 0 it left, the child to its 1
+        let memory_space = executor.get_memory_space();
+        assert_eq!(memory_space.get_value_at(0), 1);
+        assert_eq!(memory_space.get_value_at(1), 2);
+
+        executor.set_thread_limit(1);
+        load(&mut executor, "Y");
+        assert_eq!(executor.run(), Err(RuntimeError::ThreadLimitReached(1)));
+    }
+
+    #[test]
+    fn boolfuck_tapes_grow_left() {
+        let mut executor = executor(Dialect::Boolfuck, MemoryUnitType::Int8Bit);
+        executor.set_history_limit(10);
+        load(&mut executor, "+<<+");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        let cells: Vec<u32> = (0..3)
+            .map(|index| executor.get_memory_space().get_value_at(index))
+            .collect();
+        assert_eq!(cells, [1, 0, 1]);
+
+        assert!(executor.step_back());
+        assert!(executor.step_back());
+        assert_eq!(executor.get_memory_space().get_length(), 2);
+        assert_eq!(executor.get_memory_space().get_value_at(1), 1);
+    }
+
+    #[test]
+    fn moving_off_smallfuck_tapes_ends_the_program() {
+        let mut executor = executor(Dialect::Smallfuck, MemoryUnitType::Int8Bit);
+        load(&mut executor, "*<*");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        assert_eq!(executor.get_step_count(), 2);
+    }
+
+    #[test]
+    fn second_tapes_are_separate() {
+        let mut executor = executor(Dialect::Doublefuck, MemoryUnitType::Int8Bit);
+        load(&mut executor, "///{\\>+<}^/");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+
+        let tapes = executor.get_tapes();
+        assert_eq!(tapes[0].get_value_at(1), 3);
+        assert_eq!(tapes[0].get_pointer_location(), 0);
+        assert_eq!(tapes[1].get_value_at(0), 0);
+        assert_eq!(tapes[1].get_value_at(1), 1);
+        assert_eq!(tapes[1].get_pointer_location(), 1);
+    }
+
+    #[test]
+    fn second_tapes_share_the_byte_cap() {
+        let mut executor = executor(Dialect::Doublefuck, MemoryUnitType::Int8Bit);
+        executor.get_memory_space_mut().set_max_bytes(8);
+        executor.set_tape_count(1);
+        executor.set_tape_count(2);
+        executor.run_shell_command(ShellCommand::SetWidth(MemoryUnitType::Int32Bit));
+
+        for tape in executor.get_tapes() {
+            assert_eq!(tape.get_memory_type(), MemoryUnitType::Int32Bit);
+            assert_eq!(tape.get_max_length(), Some(2));
+        }
+    }
+
+    #[test]
+    fn cells_are_pushed_and_popped() {
+        let mut executor = executor(Dialect::Stack, MemoryUnitType::Int8Bit);
+        executor.set_history_limit(10);
+        load(&mut executor, "+$+$>!>!");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        let memory_space = executor.get_memory_space();
+        assert_eq!(memory_space.get_value_at(1), 2);
+        assert_eq!(memory_space.get_value_at(2), 1);
+        assert_eq!(executor.get_stack().len(), 0);
+
+        // undoing a pop puts the value back
+        assert!(executor.step_back());
+        assert_eq!(executor.get_stack().len(), 1);
+        assert_eq!(executor.get_stack().peek(), Some(1));
+
+        load(&mut executor, "!!");
+        assert_eq!(executor.run(), Err(RuntimeError::StackUnderflow));
+        executor.get_stack_mut().set_max_length(Some(1));
+        load(&mut executor, "$$");
+        assert_eq!(executor.run(), Err(RuntimeError::StackOverflow(1)));
+    }
+
+    #[test]
+    fn output_is_encoded() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int32Bit);
+        for (index, value) in [0xe9, 0x1f600, 0xd800].iter().enumerate() {
+            executor.get_memory_space_mut().set_value_at(index, *value);
+        }
+        let mut output = |encoding, invalid_output| {
+            executor.set_output_encoding(encoding, invalid_output);
+            executor.output_bytes.clear();
+            executor.get_memory_space_mut().set_pointer_location(0);
+            load(&mut executor, ".>.>.");
+            let result = executor.run().map(|_| executor.output_bytes.clone());
+            result.map(|bytes| String::from_utf8(bytes).unwrap())
+        };
+
+        assert_eq!(
+            output(OutputEncoding::Utf8, InvalidOutput::Error),
+            Err(RuntimeError::InvalidOutput(0xd800))
+        );
+        assert_eq!(
+            output(OutputEncoding::Utf8, InvalidOutput::Replace),
+            Ok(String::from("\u{e9}\u{1f600}\u{fffd}"))
+        );
+        assert_eq!(
+            output(OutputEncoding::Utf8, InvalidOutput::Skip),
+            Ok(String::from("\u{e9}\u{1f600}"))
+        );
+        assert_eq!(
+            output(OutputEncoding::Decimal, InvalidOutput::Error),
+            Ok(String::from("233 128512 55296 "))
+        );
+    }
+
+    #[test]
+    fn input_bytes_are_read_one_at_a_time() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int8Bit);
+        executor.set_input(Some("\u{e9}".as_bytes().to_vec()));
+        load(&mut executor, ",>,>+,");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        let cells: Vec<u32> = (0..3)
+            .map(|index| executor.get_memory_space().get_value_at(index))
+            .collect();
+        // the end of the input stores 0
+        assert_eq!(cells, [0xc3, 0xa9, 0]);
+
+        executor.set_input_mode(InputMode::Codepoints);
+        executor.set_input(Some("\u{1f600}".as_bytes().to_vec()));
+        load(&mut executor, ",");
+        assert_eq!(executor.run(), Err(RuntimeError::InputTooLarge(0x1f600)));
+    }
+
+    #[test]
+    fn input_characters_are_read_into_wide_cells() {
+        let mut executor = executor(Dialect::Brainfuck, MemoryUnitType::Int32Bit);
+        executor.set_input_mode(InputMode::Codepoints);
+        executor.set_input(Some("\u{e9}\u{1f600}".as_bytes().to_vec()));
+        load(&mut executor, ",>,");
+        assert_eq!(executor.run(), Ok(ExecutionStatus::Finished));
+        assert_eq!(executor.get_memory_space().get_value_at(0), 0xe9);
+        assert_eq!(executor.get_memory_space().get_value_at(1), 0x1f600);
+    }
+}
@@ -0,0 +1,21 @@
+//! brainfuck interpreter with a debugger, tracing and profiling
+//!
+//! a program is turned into commands by the `Parser` and run on a
+//! `MemorySpace` by the `Executor`
+
+pub mod bit_vector;
+pub mod bracket_pair;
+pub mod command_iterator;
+pub mod debugger;
+pub mod enums;
+pub mod executor;
+pub mod memory_space;
+pub mod memory_viewer;
+pub mod optimizer;
+pub mod parser;
+pub mod profiler;
+pub mod shell;
+pub mod span;
+pub mod stack;
+pub mod token_map;
+pub mod tracer;
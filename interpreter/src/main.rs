@@ -21,221 +21,344 @@
 
 // TODO:
 //  - make all the functions safer and add error messages
-//  - create a bracket pair struct with methods that takes care of the pairs
 //  - make state machine to make errors and exits simpler
-//  - make functionality where a code file can be supplied to execute more directly
-//  - create enum with commands
 
-use std::char;
-use std::io::{self, Write};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
 
-mod memory_space;
-use memory_space::{MemorySpace, MemoryUnitType};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
 
-mod parser;
-use parser::Parser;
+use interpreter::debugger::Debugger;
+use interpreter::enums::{Dialect, RuntimeError};
+use interpreter::executor::Executor;
+use interpreter::memory_space::MemorySpace;
+use interpreter::memory_viewer;
+use interpreter::parser::Parser;
+use interpreter::shell;
+use interpreter::token_map::TokenMap;
+use interpreter::tracer::Tracer;
 
-mod bracket_pair;
-mod command_iterator;
-mod enums;
+mod options;
+use options::{Options, USAGE};
 
-fn main() {
-    println!("Brainfuck Interpreter");
+/// name of the file in the home directory the shell history is kept in
+const HISTORY_FILE: &str = ".brainfuck_history";
 
-    let mut output_string: String = String::new();
+/// exit code for all other errors, like invalid options or a file that
+/// can't be read
+const ERROR_EXIT_CODE: i32 = 253;
 
-    // struct simulating the braifuck memory layout and functions
-    let mut mem_space = MemorySpace::new(MemoryUnitType::Int8Bit);
+/// exit code when the program could not be parsed
+const PARSE_ERROR_EXIT_CODE: i32 = 254;
 
-    let mut parser = Parser::new();
+/// exit code when the program stopped with an error
+const RUNTIME_ERROR_EXIT_CODE: i32 = 255;
 
-    // whether the program should quit
-    let mut quit = false;
+/// largest exit code a cell can set, the ones above it are reserved for errors
+const MAX_CELL_EXIT_CODE: u32 = 252;
 
-    // if the next character is a command or not
-    let mut is_command = false;
+fn main() {
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            eprintln!("{}", USAGE);
+            process::exit(ERROR_EXIT_CODE);
+        }
+    };
 
-    loop {
-        print!("bf> ");
-        match io::stdout().flush() {
-            Ok(_) => (),
-            Err(_) => {
-                eprintln!("Error: writing to screen failed");
-                break;
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match &options.file {
+        Some(path) => run_file(&options, path),
+        None => run_shell(&options),
+    }
+}
+
+/// creates an executor with the limits, watchpoints and tracing from the options
+fn create_executor(options: &Options) -> Executor {
+    let mut executor = Executor::new(options.memory_type);
+    executor.set_step_limit(options.step_limit);
+    executor.set_timeout(options.timeout);
+    executor.set_thread_limit(options.thread_limit);
+    executor
+        .get_stack_mut()
+        .set_max_length(Some(options.stack_limit));
+    executor.set_input(load_input(options));
+    executor.set_input_mode(options.input_mode);
+    executor.set_output_encoding(options.output_encoding, options.invalid_output);
+    executor.set_bounded_tape(options.dialect.get_tape_length().is_some());
+    executor.set_optimize(options.optimize);
+    executor.set_parser(create_parser(options));
+    if let Some(prefix) = options.shell_prefix {
+        executor.set_shell_prefix(prefix);
+    }
+
+    let memory_space = executor.get_memory_space_mut();
+    if let Some(max_bytes) = options.max_bytes {
+        memory_space.set_max_bytes(max_bytes);
+    }
+    if let Some(max_cells) = options.max_cells {
+        memory_space.set_max_length(Some(max_cells));
+    }
+
+    for cell in &options.watched_cells {
+        memory_space.watch_cell(*cell);
+    }
+    // boolfuck's tape is endless in both directions
+    memory_space.set_grows_left(options.dialect == Dialect::Boolfuck);
+    executor.set_tape_count(options.dialect.get_tape_count());
+
+    if let Some(path) = &options.trace_file {
+        match Tracer::create(path, options.trace_format) {
+            Ok(tracer) => executor.set_tracer(tracer),
+            Err(error) => {
+                eprintln!("Error: could not create '{}': {}", path, error);
+                process::exit(ERROR_EXIT_CODE);
             }
         }
+    }
 
-        // input string
-        let mut input = String::new();
-
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Error: could not read input");
+    executor
+}
 
-        input = input.trim().to_string();
+/// returns the bytes of the input option or the input file, None if ','
+/// should read stdin
+fn load_input(options: &Options) -> Option<Vec<u8>> {
+    let path = match &options.input_file {
+        Some(path) => path,
+        None => return options.input.clone(),
+    };
+    match fs::read(path) {
+        Ok(bytes) => Some(bytes),
+        Err(error) => {
+            eprintln!("Error: could not read '{}': {}", path, error);
+            process::exit(ERROR_EXIT_CODE);
+        }
+    }
+}
 
-        if !do_brackets_match(&input) {
-            eprintln!("Error: brackets do not match");
-            break;
+/// creates a parser with the dialect, tokens and breakpoints from the options
+fn create_parser(options: &Options) -> Parser {
+    let mut parser = Parser::new();
+    parser.set_dialect(options.dialect);
+    parser.set_split_input(options.split_input);
+    if let Some(tokens) = &options.tokens {
+        let token_map = load_token_map(tokens);
+        if options.split_input && token_map.uses_char('!') {
+            eprintln!(
+                "Error: '--split-input' can't be used with the tokens '{}', they use '!'",
+                tokens
+            );
+            process::exit(ERROR_EXIT_CODE);
         }
+        parser.set_token_map(Some(token_map));
+    }
+    if options.debug {
+        parser.set_breakpoint_char(Some(options.breakpoint_char));
+    }
+    parser
+}
+
+/// returns the built-in token map with the name or the one in the file
+fn load_token_map(name: &str) -> TokenMap {
+    match TokenMap::from_name(name) {
+        Some(token_map) => token_map,
+        None => TokenMap::from_file(name).unwrap_or_else(|message| {
+            eprintln!("Error: {}", message);
+            process::exit(ERROR_EXIT_CODE);
+        }),
+    }
+}
 
-        //println!("{}", input);
+/// runs the program in the file, in the debugger if that was requested
+fn run_file(options: &Options, path: &str) {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("Error: could not read '{}': {}", path, error);
+            process::exit(ERROR_EXIT_CODE);
+        }
+    };
 
-        let input: Vec<_> = input.chars().collect();
-        let bracket_pairs = get_bracket_pairs(&input);
+    let mut executor = create_executor(options);
 
-        let mut i = 0;
+    let mut parser = create_parser(options);
+    parser.set_file_id(executor.add_source(path));
+    if parser.parse_string(&source).is_none() {
+        eprintln!("Error: brackets do not match");
+        process::exit(PARSE_ERROR_EXIT_CODE);
+    }
 
-        loop {
-            if i >= input.len() {
-                println!("");
-                break;
+    if let Some(tokens) = &options.translate {
+        match load_token_map(tokens).translate(&parser.get_commands()) {
+            Ok(program) => print!("{}", program),
+            Err(message) => {
+                eprintln!("Error: {}", message);
+                process::exit(ERROR_EXIT_CODE);
             }
+        }
+        return;
+    }
+
+    executor.load(&parser);
+
+    if options.profile {
+        executor.enable_profiling();
+    }
+
+    if options.debug {
+        executor.set_history_limit(options.history_limit);
+        Debugger::new(executor).run();
+        return;
+    }
+
+    let result = executor.run().map(|_| ());
+
+    if options.dialect == Dialect::Brainfork {
+        eprintln!("{} threads spawned", executor.get_spawned_thread_count());
+    }
 
-            let c = input[i];
-
-            i += 1;
-
-            match c {
-                '>' => mem_space.pointer_increment(),
-                '<' => {
-                    match mem_space.pointer_decrement() {
-                        Some(_) => (),
-                        None => eprintln!("Pointer index can't be negative!"),
-                    };
-                }
-                '+' => mem_space.data_increment(),
-                '-' => mem_space.data_decrement(),
-                '.' => match mem_space.get_data_as_char() {
-                    Some(c) => {
-                        output_string.push(c);
-                        print!("{}", c);
-                        match io::stdout().flush() {
-                            Ok(_) => (),
-                            Err(_) => {
-                                eprintln!("Error: writing to screen failed");
-                                break;
-                            }
-                        }
-                    }
-                    None => break,
-                },
-                ',' => {
-                    println!("Enter 1 character:");
-                    let mut read_input = String::new();
-                    io::stdin()
-                        .read_line(&mut read_input)
-                        .expect("Error: could not read input");
-                    let c: char = match read_input.chars().next() {
-                        Some(c) => c,
-                        None => 0 as char,
-                    };
-                    mem_space.write_data(c);
-                }
-                '[' => {
-                    if mem_space.is_data_at_pointer_zero() {
-                        let index = get_second_bracket(&bracket_pairs, i - 1);
-                        match index {
-                            Some(x) => i = x,
-                            None => eprintln!("Error returning bracket pair"),
-                        };
-                    }
-                }
-                ']' => {
-                    if !mem_space.is_data_at_pointer_zero() {
-                        let index = get_first_bracket(&bracket_pairs, i - 1);
-                        match index {
-                            Some(x) => i = x,
-                            None => eprintln!("Error returning bracket pair"),
-                        };
-                    }
-                }
-                ':' => {
-                    is_command = true;
-                    continue;
-                }
-                _ => (),
-            };
-
-            if is_command {
-                is_command = false;
-                match c {
-                    'r' => {
-                        mem_space.reset();
-                        println!("Reset!");
-                    }
-                    'p' => {
-                        println!("{}", output_string);
-                        output_string = String::new();
-                    }
-                    'q' => {
-                        quit = true;
-                        println!("Quitting");
-                        break;
-                    }
-                    _ => (),
-                };
-            };
+    // the trace is also needed when the program failed
+    let flushed = executor.flush_trace();
+    if let Err(error) = result.and(flushed) {
+        eprintln!("Error: {}", executor.describe_error(error));
+        if let RuntimeError::StepLimitReached(_)
+        | RuntimeError::TimeoutReached(_)
+        | RuntimeError::TapeLimitReached(_) = error
+        {
+            if let Ok(view) = memory_viewer::format_tapes(&executor.get_tapes(), None) {
+                eprintln!("{}", view);
+            }
         }
+        process::exit(RUNTIME_ERROR_EXIT_CODE);
+    }
+
+    if let Some(profiler) = executor.get_profiler() {
+        println!();
+        print!("{}", profiler.format_report(&executor));
+    }
 
-        //println!("{:#?}; {:?}", mem_space.get_index(), mem_space.get_array());
-        if quit {
-            break;
+    if options.dump_tape {
+        dump_tape(options, &executor.get_tapes());
+    }
+
+    if options.exit_code {
+        let memory_space = executor.get_memory_space();
+        let cell = options
+            .exit_cell
+            .unwrap_or_else(|| memory_space.get_pointer_location());
+        let value = memory_space.get_value_at(cell).min(MAX_CELL_EXIT_CODE);
+        process::exit(value as i32);
+    }
+}
+
+/// prints the cells of the memory space that were selected in the options
+fn dump_tape(options: &Options, tapes: &[&MemorySpace]) {
+    match memory_viewer::format_tapes(tapes, options.dump_range.as_deref()) {
+        Ok(view) => {
+            println!();
+            println!("{}", view);
+        }
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            process::exit(ERROR_EXIT_CODE);
         }
     }
 }
 
-fn do_brackets_match(input: &String) -> bool {
-    let mut open_bracket_counter = 0;
-    let mut closed_bracket_counter = 0;
+/// runs the interactive shell that executes one program at a time, a program
+/// can span several lines as long as it has unclosed brackets
+fn run_shell(options: &Options) {
+    println!("Brainfuck Interpreter");
 
-    for c in input.chars() {
-        match c {
-            '[' => open_bracket_counter += 1,
-            ']' => closed_bracket_counter += 1,
-            _ => (),
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            eprintln!("Error: could not start the shell: {}", error);
+            process::exit(ERROR_EXIT_CODE);
         }
+    };
+
+    // the history is kept in the home directory, there is none on the first start
+    let history_path = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+    if let Some(path) = &history_path {
+        let _ = editor.load_history(path);
     }
 
-    // does the number of brackets match
-    open_bracket_counter == closed_bracket_counter
-}
+    // struct running the commands on the brainfuck memory layout
+    let mut executor = create_executor(options);
+    let file_id = executor.add_source("<shell>");
+    let shell_parser = create_parser(options);
 
-fn get_bracket_pairs(input_vec: &Vec<char>) -> Vec<(usize, usize)> {
-    let mut pair_vec: Vec<(usize, usize)> = Vec::new();
-    let mut bracket_order: Vec<usize> = Vec::new();
+    // lines of a program whose brackets are not closed yet
+    let mut pending_input = String::new();
 
-    for i in 0..input_vec.len() {
-        let c = input_vec[i];
-        match c {
-            '[' => {
-                pair_vec.push((i, 0));
-                bracket_order.push(pair_vec.len() - 1);
+    loop {
+        let prompt = if pending_input.is_empty() {
+            "bf> "
+        } else {
+            "... "
+        };
+
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            // ctrl-c throws away the program that is being typed
+            Err(ReadlineError::Interrupted) => {
+                pending_input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                eprintln!("Error: could not read input: {}", error);
+                break;
             }
-            ']' => {
-                pair_vec[bracket_order[bracket_order.len() - 1]].1 = i;
-                bracket_order.remove(bracket_order.len() - 1);
+        };
+
+        // shell commands are only recognized on a line of their own at the
+        // prompt, in a program ':' is a comment like every other character
+        let shell_command = match options.shell_prefix {
+            Some(prefix) if pending_input.is_empty() => shell::parse_line(&line, prefix),
+            _ => None,
+        };
+        if let Some(command) = shell_command {
+            let _ = editor.add_history_entry(line.trim());
+            if executor.run_shell_command(command) {
+                break;
             }
-            _ => (),
+            continue;
         }
-    }
-    pair_vec
-}
 
-fn get_first_bracket(bracket_list: &Vec<(usize, usize)>, second_bracket: usize) -> Option<usize> {
-    for pair in bracket_list.iter() {
-        if pair.1 == second_bracket {
-            return Some(pair.0);
+        pending_input.push_str(line.trim());
+        pending_input.push('\n');
+
+        if shell_parser.count_open_brackets(&pending_input) > 0 {
+            continue;
+        }
+
+        let input = std::mem::take(&mut pending_input);
+        let _ = editor.add_history_entry(input.trim_end());
+
+        let mut parser = shell_parser.clone();
+        parser.set_file_id(file_id);
+        if parser.parse_string(&input).is_none() {
+            eprintln!("Error: brackets do not match");
+            continue;
         }
+        // appending keeps the procedures defined at earlier prompts
+        executor.append(&parser);
+        executor.run_program();
     }
-    None
-}
 
-fn get_second_bracket(bracket_list: &Vec<(usize, usize)>, first_bracket: usize) -> Option<usize> {
-    for pair in bracket_list.iter() {
-        if pair.0 == first_bracket {
-            return Some(pair.1);
+    if let Some(path) = &history_path {
+        if let Err(error) = editor.save_history(path) {
+            eprintln!("Error: could not save the history: {}", error);
         }
     }
-    None
 }
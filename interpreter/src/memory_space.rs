@@ -1,114 +1,586 @@
-use crate::enums::{MemoryUnit, MemoryUnitType};
-use std::char;
+use crate::bit_vector::BitVector;
+use crate::enums::MemoryUnitType;
+
+/// first line of every snapshot of a memory space
+const SNAPSHOT_HEADER: &str = "brainfuck memory snapshot";
+
+/// write of a command to a watched cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellChange {
+    pub index: usize,
+    pub old_value: u32,
+    pub new_value: u32,
+}
+
+/// state of the memory space before a command was run, a single command
+/// can only change the pointer, the cell it points at, the storage and the
+/// tape length at either end
+#[derive(Clone, Copy, Debug)]
+pub struct UndoEntry {
+    pointer_location: usize,
+    value: u32,
+    storage: u32,
+    length: usize,
+    origin: usize,
+}
+
+/// cells of the tape, every cell takes up as many bits as it is wide and
+/// single bit cells are packed into a bit vector
+enum Tape {
+    Bytes(Vec<u8>),
+    Words(Vec<u32>),
+    Bits(BitVector),
+}
+
+impl Tape {
+    /// returns an empty tape for cells of the type
+    fn new(memory_type: MemoryUnitType) -> Self {
+        match memory_type {
+            MemoryUnitType::Bit => Tape::Bits(BitVector::new()),
+            MemoryUnitType::Int8Bit => Tape::Bytes(Vec::new()),
+            MemoryUnitType::Int32Bit => Tape::Words(Vec::new()),
+        }
+    }
+
+    /// returns the number of cells
+    fn len(&self) -> usize {
+        match self {
+            Tape::Bytes(bytes) => bytes.len(),
+            Tape::Words(words) => words.len(),
+            Tape::Bits(bits) => bits.len(),
+        }
+    }
+
+    /// makes room for the additional cells, the room grows the way it does
+    /// for a Vec but never past the limit
+    fn reserve(&mut self, additional: usize, limit: Option<usize>) {
+        match self {
+            Tape::Bytes(bytes) => Self::reserve_within(bytes, additional, limit),
+            Tape::Words(words) => Self::reserve_within(words, additional, limit),
+            Tape::Bits(bits) => {
+                let length = bits.len().saturating_add(additional);
+                let capacity = bits.len().saturating_mul(2).max(length);
+                bits.reserve_exact(limit.map_or(capacity, |limit| capacity.min(limit).max(length)));
+            }
+        }
+    }
+
+    /// makes room for the additional cells in the vector, never past the limit
+    fn reserve_within<T>(cells: &mut Vec<T>, additional: usize, limit: Option<usize>) {
+        let length = cells.len().saturating_add(additional);
+        if length <= cells.capacity() {
+            return;
+        }
+        let capacity = cells.capacity().saturating_mul(2).max(length);
+        let capacity = limit.map_or(capacity, |limit| capacity.min(limit).max(length));
+        cells.reserve_exact(capacity - cells.len());
+    }
+
+    /// returns the value of the cell at the index, None if it is past the end
+    fn get(&self, index: usize) -> Option<u32> {
+        match self {
+            Tape::Bytes(bytes) => bytes.get(index).map(|&x| x as u32),
+            Tape::Words(words) => words.get(index).copied(),
+            Tape::Bits(bits) => bits.get(index).map(u32::from),
+        }
+    }
+
+    /// sets the cell at the index to the value, it is cut off to fit
+    fn set(&mut self, index: usize, value: u32) {
+        match self {
+            Tape::Bytes(bytes) => bytes[index] = value as u8,
+            Tape::Words(words) => words[index] = value,
+            Tape::Bits(bits) => bits.set(index, value & 1 != 0),
+        }
+    }
+
+    /// adds a cell with the value to the end, it is cut off to fit
+    fn push(&mut self, value: u32) {
+        match self {
+            Tape::Bytes(bytes) => bytes.push(value as u8),
+            Tape::Words(words) => words.push(value),
+            Tape::Bits(bits) => bits.push(value & 1 != 0),
+        }
+    }
+
+    /// adds the number of cells of 0 to the start, the other cells move back
+    fn insert_front(&mut self, count: usize) {
+        match self {
+            Tape::Bytes(bytes) => {
+                bytes.splice(0..0, std::iter::repeat_n(0, count));
+            }
+            Tape::Words(words) => {
+                words.splice(0..0, std::iter::repeat_n(0, count));
+            }
+            Tape::Bits(bits) => bits.insert_front(count),
+        }
+    }
+
+    /// removes the number of cells from the start, the other cells move forward
+    fn remove_front(&mut self, count: usize) {
+        match self {
+            Tape::Bytes(bytes) => {
+                bytes.drain(..count.min(bytes.len()));
+            }
+            Tape::Words(words) => {
+                words.drain(..count.min(words.len()));
+            }
+            Tape::Bits(bits) => bits.remove_front(count),
+        }
+    }
+
+    /// removes the cells past the length
+    fn truncate(&mut self, length: usize) {
+        match self {
+            Tape::Bytes(bytes) => bytes.truncate(length),
+            Tape::Words(words) => words.truncate(length),
+            Tape::Bits(bits) => bits.truncate(length),
+        }
+    }
+}
 
 /// struct that stores the pointer location and values of the memory space
 pub struct MemorySpace {
+    memory_type: MemoryUnitType,
     pointer_location: usize,
-    memory: Vec<MemoryUnit>,
+    memory: Tape,
+    /// maximum number of cells the tape can grow to
+    max_length: Option<usize>,
+    /// maximum size of the tape in bytes, the maximum length follows from it
+    /// if it is set
+    max_bytes: Option<usize>,
+    /// indices of the cells whose writes are recorded
+    watched_cells: Vec<usize>,
+    /// writes to watched cells that were not taken yet
+    changes: Vec<CellChange>,
+    /// register the extended commands copy cells to and from
+    storage: u32,
+    /// whether moving left of the first cell adds a cell in front of it
+    grows_left: bool,
+    /// number of cells that were added in front of the first cell
+    origin: usize,
 }
 
 impl MemorySpace {
     /// takes the type of memory and returns a MemorySpace
     pub fn new(memory_type: MemoryUnitType) -> Self {
         let mut mem = Self {
+            memory_type,
             pointer_location: 0,
-            memory: Vec::new(),
-        };
-        match memory_type {
-            MemoryUnitType::Int8Bit => mem.memory.push(MemoryUnit::Int8Bit(0)),
-            MemoryUnitType::Int32Bit => mem.memory.push(MemoryUnit::Int32Bit(0)),
+            memory: Tape::new(memory_type),
+            max_length: None,
+            max_bytes: None,
+            watched_cells: Vec::new(),
+            changes: Vec::new(),
+            storage: 0,
+            grows_left: false,
+            origin: 0,
         };
+        mem.reset();
         mem
     }
 
+    /// sets the maximum number of cells the tape can grow to, None removes the limit
+    pub fn set_max_length(&mut self, max_length: Option<usize>) {
+        self.max_length = max_length;
+        self.max_bytes = None;
+    }
+
+    /// sets the maximum size of the tape in bytes, it is rounded down to whole
+    /// cells of the current and every later cell width
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = Some(max_bytes);
+        self.max_length = Self::get_max_length_for(Some(max_bytes), self.memory_type);
+    }
+
+    /// returns the number of cells of the type that fit into the bytes
+    fn get_max_length_for(max_bytes: Option<usize>, memory_type: MemoryUnitType) -> Option<usize> {
+        max_bytes.map(|max_bytes| max_bytes.saturating_mul(8) / memory_type.get_bits())
+    }
+
+    /// checks whether the cells still fit into the maximum size of the tape
+    /// if they are changed to the type
+    pub fn fits_memory_type(&self, memory_type: MemoryUnitType) -> bool {
+        self.get_max_length_with(memory_type)
+            .is_none_or(|max_length| self.memory.len() <= max_length)
+    }
+
+    /// returns the maximum number of cells the tape can grow to
+    pub fn get_max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    /// returns the maximum size of the tape in bytes, if it was given in bytes
+    pub fn get_max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
     /// action for '>', incrementing the pointer or moving it right
-    pub fn pointer_increment(&mut self) {
-        self.pointer_location += 1;
+    /// returns None if the tape would have to grow past its maximum length
+    pub fn pointer_increment(&mut self) -> Option<usize> {
+        self.pointer_increment_by(1)
+    }
 
-        if self.pointer_location >= self.memory.len() {
-            match self.memory[self.pointer_location - 1] {
-                MemoryUnit::Int8Bit(_) => {
-                    self.memory.push(MemoryUnit::Int8Bit(0));
-                }
-                MemoryUnit::Int32Bit(_) => {
-                    self.memory.push(MemoryUnit::Int32Bit(0));
-                }
-            };
+    /// moves the pointer right by the amount, like that many '>'
+    /// returns None without moving if the tape would have to grow past its
+    /// maximum length
+    pub fn pointer_increment_by(&mut self, amount: usize) -> Option<usize> {
+        let pointer_location = self.pointer_location + amount;
+        if let Some(max_length) = self.max_length {
+            if pointer_location >= max_length {
+                return None;
+            }
         }
+
+        self.pointer_location = pointer_location;
+        self.grow_to(pointer_location);
+        Some(self.pointer_location)
     }
 
-    /// action for '>', decrementing the pointer or moving it left
+    /// action for '<', decrementing the pointer or moving it left
     pub fn pointer_decrement(&mut self) -> Option<usize> {
-        if self.pointer_location >= 1 {
-            self.pointer_location -= 1;
-            Some(self.pointer_location)
-        } else {
-            None
+        self.pointer_decrement_by(1)
+    }
+
+    /// moves the pointer left by the amount, like that many '<'
+    /// returns None without moving if it would move past the first cell, or
+    /// if the tape grows left, if it would have to grow past its maximum length
+    pub fn pointer_decrement_by(&mut self, amount: usize) -> Option<usize> {
+        if self.pointer_location >= amount {
+            self.pointer_location -= amount;
+            return Some(self.pointer_location);
         }
+        if !self.grows_left {
+            return None;
+        }
+
+        let count = amount - self.pointer_location;
+        let length = self.memory.len().saturating_add(count);
+        if self.max_length.is_some_and(|max| length > max) {
+            return None;
+        }
+        self.memory.insert_front(count);
+        self.origin += count;
+        // watched cells move with the cells they watch
+        for cell in &mut self.watched_cells {
+            *cell += count;
+        }
+        self.pointer_location = 0;
+        Some(self.pointer_location)
+    }
+
+    /// sets whether moving left of the first cell adds cells in front of it,
+    /// which moves the other cells right, for tapes without a start
+    pub fn set_grows_left(&mut self, grows_left: bool) {
+        self.grows_left = grows_left;
+    }
+
+    /// checks whether moving left of the first cell adds cells in front of it
+    pub fn is_growing_left(&self) -> bool {
+        self.grows_left
     }
 
     /// action for '+', incrementing the pointer at the current location
     pub fn data_increment(&mut self) {
-        match self.memory[self.pointer_location] {
-            MemoryUnit::Int8Bit(x) => {
-                self.memory[self.pointer_location] = MemoryUnit::Int8Bit(x + 1);
-            }
-            MemoryUnit::Int32Bit(x) => {
-                self.memory[self.pointer_location] = MemoryUnit::Int32Bit(x + 1);
-            }
-        };
+        self.data_add(1);
     }
 
     /// action for '-', decrementing the pointer at the current location
     pub fn data_decrement(&mut self) {
-        match self.memory[self.pointer_location] {
-            MemoryUnit::Int8Bit(x) => {
-                self.memory[self.pointer_location] = MemoryUnit::Int8Bit(x - 1);
-            }
-            MemoryUnit::Int32Bit(x) => {
-                self.memory[self.pointer_location] = MemoryUnit::Int32Bit(x - 1);
-            }
+        self.data_add(-1);
+    }
+
+    /// adds the amount to the cell at the current location, like that many
+    /// '+' or '-', the value wraps around
+    pub fn data_add(&mut self, amount: i32) {
+        let old_value = self.get_value_at(self.pointer_location);
+        // cutting off the wrapped sum wraps it for every cell width
+        self.memory
+            .set(self.pointer_location, old_value.wrapping_add(amount as u32));
+        self.record_write(self.pointer_location, old_value);
+    }
+
+    /// stores the value in the current pointer position, it is cut off to fit
+    pub fn set_data(&mut self, value: u32) {
+        let old_value = self.get_value_at(self.pointer_location);
+        self.memory.set(self.pointer_location, value);
+        self.record_write(self.pointer_location, old_value);
+    }
+
+    /// stores the value in the cell at the index, growing the tape up to it
+    /// returns None if the index is past the maximum length of the tape
+    pub fn set_value_at(&mut self, index: usize, value: u32) -> Option<()> {
+        if self.max_length.is_some_and(|max| index >= max) {
+            return None;
+        }
+        self.grow_to(index);
+        self.memory.set(index, value);
+        Some(())
+    }
+
+    /// like set_value_at, but for writes of a command, which are remembered
+    /// if the cell is watched
+    pub fn write_value_at(&mut self, index: usize, value: u32) -> Option<()> {
+        let old_value = self.get_value_at(index);
+        self.set_value_at(index, value)?;
+        self.record_write(index, old_value);
+        Some(())
+    }
+
+    /// returns the value of the storage register
+    pub fn get_storage(&self) -> u32 {
+        self.storage
+    }
+
+    /// sets the storage register, the value is cut off to fit into a cell
+    pub fn set_storage(&mut self, value: u32) {
+        self.storage = match self.memory_type {
+            MemoryUnitType::Bit => value & 1,
+            MemoryUnitType::Int8Bit => value as u8 as u32,
+            MemoryUnitType::Int32Bit => value,
         };
     }
 
-    /// action for '.', return the ascii variant of the number
-    /// at the current pointer position
-    pub fn get_data_as_char(&self) -> Option<char> {
-        let mut c: u32 = 0;
-        match self.memory[self.pointer_location] {
-            MemoryUnit::Int8Bit(x) => {
-                c = x as u32;
-            }
-            MemoryUnit::Int32Bit(x) => {
-                c = x;
+    /// changes the type of the cells, values that do not fit are cut off, a
+    /// maximum size in bytes is turned into cells of the new type
+    pub fn set_memory_type(&mut self, memory_type: MemoryUnitType) {
+        let mut memory = Tape::new(memory_type);
+        memory.reserve(self.memory.len(), None);
+        for index in 0..self.memory.len() {
+            memory.push(self.get_value_at(index));
+        }
+        self.memory = memory;
+        self.memory_type = memory_type;
+        self.max_length = self.get_max_length_with(memory_type);
+        self.set_storage(self.storage);
+    }
+
+    /// reset the pointer and memory to standard states, a single cell of the
+    /// configured type
+    pub fn reset(&mut self) {
+        self.pointer_location = 0;
+        self.memory = Tape::new(self.memory_type);
+        self.memory.push(0);
+        self.storage = 0;
+        self.origin = 0;
+    }
+
+    /// returns a text snapshot of the cell type, pointer and all cells of
+    /// every tape
+    pub fn to_snapshot(tapes: &[&MemorySpace]) -> String {
+        let mut snapshot = format!("{}\n", SNAPSHOT_HEADER);
+        for tape in tapes {
+            let cells: Vec<String> = (0..tape.memory.len())
+                .map(|index| tape.get_value_at(index).to_string())
+                .collect();
+            snapshot.push_str(&format!(
+                "cell bits {}\npointer {}\ncells {}\n",
+                tape.memory_type.get_bits(),
+                tape.pointer_location,
+                cells.join(" ")
+            ));
+        }
+        snapshot
+    }
+
+    /// checks whether the text is a snapshot made by to_snapshot
+    pub fn is_snapshot(text: &str) -> bool {
+        text.lines().next() == Some(SNAPSHOT_HEADER)
+    }
+
+    /// replaces the cell type, pointer and cells of every tape with the ones
+    /// in the snapshot, it has to have as many tapes with cells of one width
+    /// returns an error message and leaves the tapes as they are if the
+    /// snapshot is invalid or does not fit into the maximum size of a tape
+    pub fn load_snapshot(tapes: &mut [&mut MemorySpace], snapshot: &str) -> Result<(), String> {
+        let invalid = || String::from("it is not a valid snapshot");
+        let mut lines = snapshot.lines();
+        if lines.next() != Some(SNAPSHOT_HEADER) {
+            return Err(invalid());
+        }
+        let mut loaded = Vec::new();
+        while let Some(line) = lines.next() {
+            loaded.push(Self::parse_snapshot_tape(line, &mut lines).ok_or_else(invalid)?);
+        }
+
+        if loaded.len() != tapes.len() {
+            return Err(format!(
+                "it has {} tapes, not {}",
+                loaded.len(),
+                tapes.len()
+            ));
+        }
+        if loaded.windows(2).any(|pair| pair[0].0 != pair[1].0) {
+            return Err(String::from("the cells of its tapes differ in width"));
+        }
+        for (tape, (memory_type, _, memory)) in tapes.iter().zip(&loaded) {
+            if tape
+                .get_max_length_with(*memory_type)
+                .is_some_and(|max| memory.len() > max)
+            {
+                return Err(format!(
+                    "its tape of {} cells is longer than the maximum",
+                    memory.len()
+                ));
             }
-        };
-        char::from_u32(c)
+        }
+
+        for (tape, (memory_type, pointer_location, memory)) in tapes.iter_mut().zip(loaded) {
+            tape.max_length = tape.get_max_length_with(memory_type);
+            tape.memory_type = memory_type;
+            tape.pointer_location = pointer_location;
+            tape.memory = memory;
+            tape.set_storage(tape.storage);
+        }
+        Ok(())
     }
 
-    /// action for ',', store one character in the current pointer position
-    pub fn write_data(&mut self, c: char) {
-        match self.memory[self.pointer_location] {
-            MemoryUnit::Int8Bit(_) => {
-                self.memory[self.pointer_location] = MemoryUnit::Int8Bit(c as u8);
+    /// parses the cell type, pointer and cells of one tape of a snapshot,
+    /// starting with the line that was already taken
+    /// returns None if they are invalid
+    fn parse_snapshot_tape<'a>(
+        first_line: &str,
+        lines: &mut impl Iterator<Item = &'a str>,
+    ) -> Option<(MemoryUnitType, usize, Tape)> {
+        let memory_type = match first_line.strip_prefix("cell bits ")? {
+            "1" => MemoryUnitType::Bit,
+            "8" => MemoryUnitType::Int8Bit,
+            "32" => MemoryUnitType::Int32Bit,
+            _ => return None,
+        };
+        let pointer_location: usize = lines.next()?.strip_prefix("pointer ")?.parse().ok()?;
+
+        let mut memory = Tape::new(memory_type);
+        for value in lines.next()?.strip_prefix("cells")?.split_whitespace() {
+            let value: u32 = value.parse().ok()?;
+            if value > memory_type.get_max_value() {
+                return None;
             }
-            MemoryUnit::Int32Bit(_) => {
-                self.memory[self.pointer_location] = MemoryUnit::Int32Bit(c as u32);
+            memory.push(value);
+        }
+
+        if pointer_location >= memory.len() {
+            return None;
+        }
+        Some((memory_type, pointer_location, memory))
+    }
+
+    /// returns the maximum number of cells the tape could grow to with cells
+    /// of the type, a maximum size in bytes is turned into cells of it
+    fn get_max_length_with(&self, memory_type: MemoryUnitType) -> Option<usize> {
+        match self.max_bytes {
+            Some(_) => Self::get_max_length_for(self.max_bytes, memory_type),
+            None => self.max_length,
+        }
+    }
+
+    /// adds cells of 0 to the end of the tape until the index is on it
+    fn grow_to(&mut self, index: usize) {
+        if index < self.memory.len() {
+            return;
+        }
+        self.memory
+            .reserve(index + 1 - self.memory.len(), self.max_length);
+        while index >= self.memory.len() {
+            self.memory.push(0);
+        }
+    }
+
+    /// returns the current location of the pointer
+    pub fn get_pointer_location(&self) -> usize {
+        self.pointer_location
+    }
+
+    /// moves the pointer to the index, the tape grows up to it if needed
+    pub fn set_pointer_location(&mut self, index: usize) {
+        self.grow_to(index);
+        self.pointer_location = index;
+    }
+
+    /// returns the type of the memory cells
+    pub fn get_memory_type(&self) -> MemoryUnitType {
+        self.memory_type
+    }
+
+    /// returns the number of cells the pointer has reached so far
+    pub fn get_length(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// returns the value of the cell at the index, cells that were never
+    /// reached by the pointer are 0
+    pub fn get_value_at(&self, index: usize) -> u32 {
+        self.memory.get(index).unwrap_or(0)
+    }
+
+    /// returns what is needed to undo the next command
+    pub fn get_undo_entry(&self) -> UndoEntry {
+        UndoEntry {
+            pointer_location: self.pointer_location,
+            value: self.get_value_at(self.pointer_location),
+            storage: self.storage,
+            length: self.memory.len(),
+            origin: self.origin,
+        }
+    }
+
+    /// restores the state the memory space was in when the entry was taken
+    pub fn undo(&mut self, entry: UndoEntry) {
+        if self.origin > entry.origin {
+            let count = self.origin - entry.origin;
+            self.memory.remove_front(count);
+            for cell in &mut self.watched_cells {
+                *cell = cell.saturating_sub(count);
             }
-        };
+            self.origin = entry.origin;
+        }
+        self.memory.truncate(entry.length);
+        self.pointer_location = entry.pointer_location;
+        self.storage = entry.storage;
+        if entry.pointer_location < self.memory.len() {
+            self.memory.set(entry.pointer_location, entry.value);
+        }
     }
 
-    /// reset the pointer and memory to standard states
-    pub fn reset(&mut self) {
-        self.pointer_location = 0;
-        self.memory = Vec::new();
+    /// records writes to the cell at the index from now on
+    pub fn watch_cell(&mut self, index: usize) {
+        if !self.watched_cells.contains(&index) {
+            self.watched_cells.push(index);
+        }
+    }
+
+    /// stops recording writes to the cell at the index
+    /// returns false if the cell was not watched
+    pub fn unwatch_cell(&mut self, index: usize) -> bool {
+        let length = self.watched_cells.len();
+        self.watched_cells.retain(|cell| *cell != index);
+        self.watched_cells.len() != length
+    }
+
+    /// returns the indices of all watched cells
+    pub fn get_watched_cells(&self) -> &[usize] {
+        &self.watched_cells
+    }
+
+    /// returns the writes to watched cells since the last call, in the order
+    /// they were made
+    pub fn take_changes(&mut self) -> Vec<CellChange> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// remembers the write to the cell at the index if the cell is watched
+    fn record_write(&mut self, index: usize, old_value: u32) {
+        if self.watched_cells.contains(&index) {
+            self.changes.push(CellChange {
+                index,
+                old_value,
+                new_value: self.get_value_at(index),
+            });
+        }
     }
 
     /// checks whether the pointer is pointing at 0
     pub fn is_data_at_pointer_zero(&self) -> bool {
-        match self.memory[self.pointer_location] {
-            MemoryUnit::Int8Bit(x) => x == 0,
-            MemoryUnit::Int32Bit(x) => x == 0,
-        }
+        self.get_value_at(self.pointer_location) == 0
     }
 }
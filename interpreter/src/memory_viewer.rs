@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+use crate::enums::MemoryUnitType;
+use crate::memory_space::MemorySpace;
+
+/// number of cells shown in one row
+const CELLS_PER_ROW: usize = 8;
+
+/// parses a range of cells like "10..20", "10..", "..20" or "10"
+/// a missing end means up to the end of the tape, an end past it is moved
+/// to it, None is returned if the range does not start on the tape
+pub fn parse_range(string: &str, length: usize) -> Option<Range<usize>> {
+    let range = match string.split_once("..") {
+        Some((start, end)) => {
+            let start = match start.trim() {
+                "" => 0,
+                start => start.parse().ok()?,
+            };
+            let end = match end.trim() {
+                "" => length,
+                end => end.parse().ok()?,
+            };
+            start..end
+        }
+        None => {
+            let index: usize = string.trim().parse().ok()?;
+            index..index.checked_add(1)?
+        }
+    };
+
+    let end = range.end.min(length);
+    if range.start < end {
+        Some(range.start..end)
+    } else {
+        None
+    }
+}
+
+/// returns the views of the cells in the range of all tapes, every one after
+/// a line with the number of the tape if there is more than one, all cells
+/// of a tape are shown if there is no range
+/// returns an error message if the range is invalid
+pub fn format_tapes(tapes: &[&MemorySpace], range: Option<&str>) -> Result<String, String> {
+    let mut views = Vec::new();
+    for (number, memory_space) in tapes.iter().enumerate() {
+        let length = memory_space.get_length();
+        let range = match range {
+            Some(range) => {
+                parse_range(range, length).ok_or_else(|| format!("invalid range '{}'", range))?
+            }
+            None => 0..length,
+        };
+        let view = format_memory(memory_space, range);
+        views.push(match tapes.len() {
+            1 => view,
+            _ => format!("tape {}:\n{}", number + 1, view),
+        });
+    }
+    Ok(views.join("\n"))
+}
+
+/// returns a hexdump-style view of the cells in the range, every row shows
+/// the index of its first cell followed by the cells in hex, decimal and
+/// ascii, the cell at the pointer is put in brackets
+pub fn format_memory(memory_space: &MemorySpace, range: Range<usize>) -> String {
+    let (hex_width, decimal_width) = match memory_space.get_memory_type() {
+        MemoryUnitType::Bit => (1, 1),
+        MemoryUnitType::Int8Bit => (2, 3),
+        MemoryUnitType::Int32Bit => (8, 10),
+    };
+    let pointer = memory_space.get_pointer_location();
+    let mut view = String::new();
+
+    let mut row_start = range.start;
+    while row_start < range.end {
+        let row_end = (row_start + CELLS_PER_ROW).min(range.end);
+
+        let mut hex = String::new();
+        let mut decimal = String::new();
+        let mut ascii = String::new();
+
+        for index in row_start..row_end {
+            let value = memory_space.get_value_at(index);
+            let (open, close) = if index == pointer {
+                ('[', ']')
+            } else {
+                (' ', ' ')
+            };
+
+            hex.push_str(&format!(
+                "{}{:0width$x}{}",
+                open,
+                value,
+                close,
+                width = hex_width
+            ));
+            decimal.push_str(&format!(
+                "{}{:>width$}{}",
+                open,
+                value,
+                close,
+                width = decimal_width
+            ));
+            ascii.push(match value {
+                0x20..=0x7e => value as u8 as char,
+                _ => '.',
+            });
+        }
+
+        // pad short rows so that the columns line up
+        for _ in row_end..row_start + CELLS_PER_ROW {
+            hex.push_str(&" ".repeat(hex_width + 2));
+            decimal.push_str(&" ".repeat(decimal_width + 2));
+        }
+
+        view.push_str(&format!(
+            "{:>8} |{}|{}| {}\n",
+            row_start, hex, decimal, ascii
+        ));
+        row_start = row_end;
+    }
+
+    view.push_str(&format!(
+        "pointer {}, {} cells in use",
+        pointer,
+        memory_space.get_length()
+    ));
+    view
+}
@@ -0,0 +1,150 @@
+use crate::enums::BrainfuckCommand::{
+    ClosedBracket, DataDecrement, DataIncrement, OpenBracket, PointerDecrement, PointerIncrement,
+};
+use crate::enums::{Command, OptimizedCommand};
+use crate::span::Span;
+
+/// replaces runs of brainfuck commands with optimized commands that do the
+/// same in one step, every optimized command gets a span that covers all the
+/// commands it replaces
+pub fn optimize(commands: &[Command], spans: &[Span]) -> (Vec<Command>, Vec<Span>) {
+    let mut optimized_commands = Vec::new();
+    let mut optimized_spans = Vec::new();
+
+    let mut index = 0;
+    while index < commands.len() {
+        let (command, length) = match &commands[index..] {
+            [Command::Brainfuck(OpenBracket), Command::Brainfuck(DataDecrement | DataIncrement), Command::Brainfuck(ClosedBracket), ..] => {
+                (Command::Optimized(OptimizedCommand::ClearData), 3)
+            }
+            [Command::Brainfuck(DataIncrement | DataDecrement), ..] => {
+                let length = count_run(&commands[index..], |command| {
+                    matches!(command, Command::Brainfuck(DataIncrement | DataDecrement))
+                });
+                let amount =
+                    commands[index..index + length]
+                        .iter()
+                        .fold(0i32, |amount, command| match command {
+                            Command::Brainfuck(DataIncrement) => amount.wrapping_add(1),
+                            _ => amount.wrapping_sub(1),
+                        });
+                (
+                    Command::Optimized(OptimizedCommand::AddData(amount)),
+                    length,
+                )
+            }
+            [first @ Command::Brainfuck(PointerIncrement | PointerDecrement), ..] => {
+                let length = count_run(&commands[index..], |command| command == first);
+                let amount = match first {
+                    Command::Brainfuck(PointerIncrement) => length as isize,
+                    _ => -(length as isize),
+                };
+                (
+                    Command::Optimized(OptimizedCommand::MovePointer(amount)),
+                    length,
+                )
+            }
+            [command, ..] => (command.clone(), 1),
+            [] => break,
+        };
+
+        // single commands are kept, so that they show up as they were written
+        if length == 1 {
+            optimized_commands.push(commands[index].clone());
+        } else {
+            optimized_commands.push(command);
+        }
+        optimized_spans.push(spans[index].merge(&spans[index + length - 1]));
+        index += length;
+    }
+
+    (optimized_commands, optimized_spans)
+}
+
+/// returns how many commands at the start of the slice match
+fn count_run(commands: &[Command], matches: impl Fn(&Command) -> bool) -> usize {
+    commands
+        .iter()
+        .take_while(|command| matches(command))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::{BrainfuckCommand, MemoryUnitType};
+    use crate::executor::Executor;
+    use crate::parser::Parser;
+
+    /// returns the pointer and all cells after running the program
+    fn run(source: &str, memory_type: MemoryUnitType, optimize: bool) -> (usize, Vec<u32>) {
+        let parser = Parser::from_string(source).unwrap();
+        let mut executor = Executor::new(memory_type);
+        executor.set_optimize(optimize);
+        executor.load(&parser);
+        executor.run().unwrap();
+
+        let memory_space = executor.get_memory_space();
+        let cells = (0..memory_space.get_length())
+            .map(|index| memory_space.get_value_at(index))
+            .collect();
+        (memory_space.get_pointer_location(), cells)
+    }
+
+    #[test]
+    fn runs_are_merged() {
+        let parser = Parser::from_string("+++-->><[-]").unwrap();
+        let (commands, spans) = optimize(&parser.get_commands(), &parser.get_spans());
+
+        assert_eq!(
+            commands,
+            vec![
+                Command::Optimized(OptimizedCommand::AddData(1)),
+                Command::Optimized(OptimizedCommand::MovePointer(2)),
+                Command::Brainfuck(BrainfuckCommand::PointerDecrement),
+                Command::Optimized(OptimizedCommand::ClearData),
+            ]
+        );
+        let spans: Vec<_> = spans
+            .iter()
+            .map(|span| (span.get_offset(), span.get_length()))
+            .collect();
+        assert_eq!(spans, vec![(0, 5), (5, 2), (7, 1), (8, 3)]);
+    }
+
+    #[test]
+    fn single_commands_are_kept() {
+        let parser = Parser::from_string("+>-<[.]").unwrap();
+        let (commands, _) = optimize(&parser.get_commands(), &parser.get_spans());
+        assert_eq!(commands, parser.get_commands());
+    }
+
+    #[test]
+    fn optimized_run_matches_plain_run() {
+        let programs = [
+            "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>",
+            "+++++[>+++++<-]>[<++>-]<[-]>>---",
+            "++>+++++[<+>-]++++++++[<++++++>-]<",
+        ];
+        // these only end in a reasonable time if the cells wrap at 8 bits
+        let wrapping_programs = ["-[--->+<]>-", ">>>+++[<<+[+]>>-]<<<----------"];
+
+        let runs = programs
+            .iter()
+            .map(|program| (*program, MemoryUnitType::Int32Bit))
+            .chain(
+                programs
+                    .iter()
+                    .chain(&wrapping_programs)
+                    .map(|program| (*program, MemoryUnitType::Int8Bit)),
+            );
+        for (program, memory_type) in runs {
+            assert_eq!(
+                run(program, memory_type, true),
+                run(program, memory_type, false),
+                "{}",
+                program
+            );
+        }
+    }
+}
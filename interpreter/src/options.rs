@@ -0,0 +1,461 @@
+use std::time::Duration;
+
+use interpreter::enums::{
+    Dialect, InputMode, InvalidOutput, MemoryUnitType, OutputEncoding, TraceFormat,
+};
+
+/// usage text that is printed for '--help'
+pub const USAGE: &str = "\
+Usage: interpreter [OPTIONS] [FILE]
+
+Runs the brainfuck program in FILE, or starts the interactive shell if no
+FILE is given.
+
+The exit code is 254 if FILE could not be parsed, 255 if the program stopped
+with an error and 253 for other errors, like invalid options or files that
+can't be read.
+
+Options:
+  -d, --debug                 run FILE in the debugger
+  -w, --cell-width <1|8|32>   number of bits in a memory cell (default 8, 1 for
+                              boolfuck and smallfuck)
+  -O, --optimize              combine runs of commands before running them
+      --dialect <NAME>        language of the program, 'brainfuck' (default),
+                              'extended' for Extended Brainfuck Type I,
+                              'pbrain', 'brainfork', 'boolfuck',
+                              'smallfuck', 'doublefuck' or 'stack'
+      --max-threads <N>       stop a Brainfork program if more than N threads
+                              run at once (default 1024)
+      --max-stack <N>         stop a stack program if it pushes more than N
+                              values (default 1024)
+      --shell-prefix <C>      character shell commands start with, 'none'
+                              turns them off (default ':', none for pbrain
+                              and doublefuck)
+      --tokens <NAME|FILE>    tokens the commands are written as, 'ook',
+                              'blub', 'alphuck' or a JSON or TOML file that
+                              maps command characters to tokens
+      --translate <NAME|FILE> print FILE written with other tokens instead of
+                              running it
+      --input <TEXT>          bytes ',' reads instead of asking for them
+      --input-file <FILE>     read the bytes for ',' from FILE
+      --input-hex <HEX>       bytes for ',' in hex, like '48 69 0a'
+      --output-encoding <ENC> how '.' writes a cell, 'utf8' for the character
+                              with the value as its codepoint (default for 32
+                              bit cells), 'bytes' for the lowest 8 bits or
+                              'decimal'
+      --invalid-output <WHAT> what '.' does with a value that is no character,
+                              'error' (default), 'replace' or 'skip'
+      --input-mode <MODE>     how ',' reads the input, 'bytes' for one byte of
+                              the UTF-8 at a time or 'codepoints' for a whole
+                              character (default for 32 bit cells), a value
+                              that does not fit into a cell is an error
+      --split-input           run the code before the first '!' with the text
+                              after it as its input, not for dialects and
+                              tokens that use '!'
+      --max-steps <N>         stop the program after N commands
+      --timeout <SECONDS>     stop the program after it ran for SECONDS
+      --max-cells <N>         stop the program if the tape grows past N cells,
+                              the length of the tape for smallfuck (default
+                              256)
+      --max-bytes <N>         stop the program if the tape grows past N bytes
+      --history <N>           number of commands the debugger can undo
+                              (default 10000)
+      --trace <FILE>          write every command that is run to FILE
+      --trace-format <FORMAT> 'text' (default) or 'binary'
+      --profile               count how often every command is run and print
+                              the hottest loops and an annotated listing
+      --dump-tape             print the tape after the program has finished
+      --dump-range <RANGE>    only dump the cells in the range, like '10..20',
+                              '10..' or '10'
+      --watch <CELL>          report every write to the cell, can be given
+                              more than once
+      --exit-code             exit with the value of the cell at the pointer
+                              when FILE has finished, values above 252 exit
+                              with 252, not in the debugger
+      --exit-cell <CELL>      exit with the value of CELL instead
+      --breakpoint-char <C>   character that marks a breakpoint in the source
+                              when debugging (default '#')
+  -h, --help                  print this help";
+
+/// struct holding the options the interpreter was started with
+pub struct Options {
+    /// path of the program to run, the shell is started if there is none
+    pub file: Option<String>,
+    /// type of the memory cells
+    pub memory_type: MemoryUnitType,
+    /// whether the program is run in the debugger
+    pub debug: bool,
+    /// character that is parsed as a breakpoint when debugging
+    pub breakpoint_char: char,
+    /// maximum number of commands a program can run
+    pub step_limit: Option<u64>,
+    /// maximum time a program can run for
+    pub timeout: Option<Duration>,
+    /// maximum number of cells the tape can grow to
+    pub max_cells: Option<usize>,
+    /// maximum number of bytes the tape can grow to
+    pub max_bytes: Option<usize>,
+    /// number of commands the debugger can undo
+    pub history_limit: usize,
+    /// indices of the cells whose writes are reported
+    pub watched_cells: Vec<usize>,
+    /// file every command that is run is written to
+    pub trace_file: Option<String>,
+    /// format of the trace file
+    pub trace_format: TraceFormat,
+    /// language the programs are written in
+    pub dialect: Dialect,
+    /// maximum number of Brainfork threads that can run at once
+    pub thread_limit: usize,
+    /// maximum number of values on the stack of the stack dialect
+    pub stack_limit: usize,
+    /// character shell commands start with, None if there are no shell commands
+    pub shell_prefix: Option<char>,
+    /// built-in name or file of the tokens the programs are written as
+    pub tokens: Option<String>,
+    /// built-in name or file of the tokens the program is translated to
+    pub translate: Option<String>,
+    /// bytes ',' reads instead of stdin
+    pub input: Option<Vec<u8>>,
+    /// file ',' reads from instead of stdin
+    pub input_file: Option<String>,
+    /// how ',' turns the input into cell values
+    pub input_mode: InputMode,
+    /// how '.' writes the value of a cell
+    pub output_encoding: OutputEncoding,
+    /// what '.' does with values that are not valid characters
+    pub invalid_output: InvalidOutput,
+    /// whether the source is split at the first '!' into code and its input
+    pub split_input: bool,
+    /// whether runs of commands are combined before they are run
+    pub optimize: bool,
+    /// whether a profile is printed after the program has finished
+    pub profile: bool,
+    /// whether the tape is printed after the program has finished
+    pub dump_tape: bool,
+    /// cells that are printed when dumping the tape, all of them if None
+    pub dump_range: Option<String>,
+    /// whether the exit code is the value of a cell after the program
+    pub exit_code: bool,
+    /// cell whose value is the exit code, the one at the pointer if None
+    pub exit_cell: Option<usize>,
+    /// whether only the usage text should be printed
+    pub help: bool,
+}
+
+impl Options {
+    /// parses the command line arguments, without the program name
+    /// returns an error message if the arguments are invalid
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut options = Self {
+            file: None,
+            memory_type: MemoryUnitType::Int8Bit,
+            debug: false,
+            breakpoint_char: '#',
+            step_limit: None,
+            timeout: None,
+            max_cells: None,
+            max_bytes: None,
+            history_limit: 10000,
+            watched_cells: Vec::new(),
+            trace_file: None,
+            trace_format: TraceFormat::Text,
+            dialect: Dialect::Brainfuck,
+            thread_limit: 1024,
+            stack_limit: 1024,
+            shell_prefix: Some(':'),
+            tokens: None,
+            translate: None,
+            input: None,
+            input_file: None,
+            input_mode: InputMode::Bytes,
+            output_encoding: OutputEncoding::Utf8,
+            invalid_output: InvalidOutput::Error,
+            split_input: false,
+            optimize: false,
+            profile: false,
+            dump_tape: false,
+            dump_range: None,
+            exit_code: false,
+            exit_cell: None,
+            help: false,
+        };
+
+        // the default prefix depends on the dialect and the default input mode
+        // and output encoding on the cell width, which can come later
+        let mut shell_prefix = None;
+        let mut input_mode = None;
+        let mut output_encoding = None;
+        // the input options that were given, only one of them can be
+        let mut input_options = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-w" | "--cell-width" => {
+                    options.memory_type = match Self::get_value(&arg, &mut args)?.as_str() {
+                        "1" => MemoryUnitType::Bit,
+                        "8" => MemoryUnitType::Int8Bit,
+                        "32" => MemoryUnitType::Int32Bit,
+                        _ => return Err(format!("'{}' expects 1, 8 or 32", arg)),
+                    };
+                }
+                "-d" | "--debug" => options.debug = true,
+                "--breakpoint-char" => {
+                    let value = Self::get_value(&arg, &mut args)?;
+                    let mut chars = value.chars();
+                    options.breakpoint_char = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => return Err(format!("'{}' expects a single character", arg)),
+                    };
+                }
+                "--max-steps" => match Self::get_value(&arg, &mut args)?.parse() {
+                    Ok(steps) => options.step_limit = Some(steps),
+                    Err(_) => return Err(format!("'{}' expects a number", arg)),
+                },
+                "--timeout" => match Self::get_value(&arg, &mut args)?.parse::<f64>() {
+                    Ok(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+                        options.timeout = Some(Duration::from_secs_f64(seconds));
+                    }
+                    _ => return Err(format!("'{}' expects a number of seconds", arg)),
+                },
+                "--max-cells" => match Self::get_value(&arg, &mut args)?.parse() {
+                    Ok(cells) if cells > 0 => options.max_cells = Some(cells),
+                    _ => return Err(format!("'{}' expects a positive number", arg)),
+                },
+                "--max-bytes" => match Self::get_value(&arg, &mut args)?.parse() {
+                    Ok(bytes) if bytes > 0 => options.max_bytes = Some(bytes),
+                    _ => return Err(format!("'{}' expects a positive number", arg)),
+                },
+                "--max-threads" => match Self::get_value(&arg, &mut args)?.parse() {
+                    Ok(threads) if threads > 0 => options.thread_limit = threads,
+                    _ => return Err(format!("'{}' expects a positive number", arg)),
+                },
+                "--max-stack" => match Self::get_value(&arg, &mut args)?.parse() {
+                    Ok(values) => options.stack_limit = values,
+                    Err(_) => return Err(format!("'{}' expects a number", arg)),
+                },
+                "--history" => match Self::get_value(&arg, &mut args)?.parse() {
+                    Ok(limit) => options.history_limit = limit,
+                    Err(_) => return Err(format!("'{}' expects a number", arg)),
+                },
+                "--watch" => match Self::get_value(&arg, &mut args)?.parse() {
+                    Ok(cell) => options.watched_cells.push(cell),
+                    Err(_) => return Err(format!("'{}' expects a cell index", arg)),
+                },
+                "--trace" => options.trace_file = Some(Self::get_value(&arg, &mut args)?),
+                "--trace-format" => {
+                    options.trace_format = match Self::get_value(&arg, &mut args)?.as_str() {
+                        "text" => TraceFormat::Text,
+                        "binary" => TraceFormat::Binary,
+                        _ => return Err(format!("'{}' expects 'text' or 'binary'", arg)),
+                    };
+                }
+                "-O" | "--optimize" => options.optimize = true,
+                "--input" => {
+                    options.input = Some(Self::get_value(&arg, &mut args)?.into_bytes());
+                    input_options.push(arg);
+                }
+                "--input-file" => {
+                    options.input_file = Some(Self::get_value(&arg, &mut args)?);
+                    input_options.push(arg);
+                }
+                "--input-hex" => {
+                    match Self::parse_hex(&Self::get_value(&arg, &mut args)?) {
+                        Some(bytes) => options.input = Some(bytes),
+                        None => return Err(format!("'{}' expects pairs of hex digits", arg)),
+                    }
+                    input_options.push(arg);
+                }
+                "--input-mode" => {
+                    input_mode = match Self::get_value(&arg, &mut args)?.as_str() {
+                        "bytes" => Some(InputMode::Bytes),
+                        "codepoints" => Some(InputMode::Codepoints),
+                        _ => return Err(format!("'{}' expects 'bytes' or 'codepoints'", arg)),
+                    };
+                }
+                "--output-encoding" => {
+                    output_encoding = match Self::get_value(&arg, &mut args)?.as_str() {
+                        "utf8" => Some(OutputEncoding::Utf8),
+                        "bytes" => Some(OutputEncoding::Bytes),
+                        "decimal" => Some(OutputEncoding::Decimal),
+                        _ => return Err(format!("'{}' expects 'utf8', 'bytes' or 'decimal'", arg)),
+                    };
+                }
+                "--invalid-output" => {
+                    options.invalid_output = match Self::get_value(&arg, &mut args)?.as_str() {
+                        "error" => InvalidOutput::Error,
+                        "replace" => InvalidOutput::Replace,
+                        "skip" => InvalidOutput::Skip,
+                        _ => return Err(format!("'{}' expects 'error', 'replace' or 'skip'", arg)),
+                    };
+                }
+                "--split-input" => options.split_input = true,
+                "--shell-prefix" => {
+                    let value = Self::get_value(&arg, &mut args)?;
+                    let mut chars = value.chars();
+                    shell_prefix = match (chars.next(), chars.next()) {
+                        _ if value == "none" => Some(None),
+                        (Some(c), None) if !c.is_whitespace() => Some(Some(c)),
+                        _ => return Err(format!("'{}' expects a character or 'none'", arg)),
+                    };
+                }
+                "--tokens" => options.tokens = Some(Self::get_value(&arg, &mut args)?),
+                "--translate" => options.translate = Some(Self::get_value(&arg, &mut args)?),
+                "--dialect" => {
+                    let name = Self::get_value(&arg, &mut args)?;
+                    options.dialect = Dialect::from_name(&name)
+                        .ok_or_else(|| format!("unknown dialect '{}'", name))?;
+                }
+                "--profile" => options.profile = true,
+                "--dump-tape" => options.dump_tape = true,
+                "--dump-range" => {
+                    options.dump_range = Some(Self::get_value(&arg, &mut args)?);
+                    options.dump_tape = true;
+                }
+                "--exit-code" => options.exit_code = true,
+                "--exit-cell" => match Self::get_value(&arg, &mut args)?.parse() {
+                    Ok(cell) => {
+                        options.exit_cell = Some(cell);
+                        options.exit_code = true;
+                    }
+                    Err(_) => return Err(format!("'{}' expects a cell index", arg)),
+                },
+                "-h" | "--help" => options.help = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option '{}'", arg)),
+                _ => {
+                    if options.file.is_some() {
+                        return Err(String::from("only one file can be run"));
+                    }
+                    options.file = Some(arg);
+                }
+            }
+        }
+
+        options.shell_prefix = shell_prefix.unwrap_or_else(|| options.dialect.get_shell_prefix());
+        if let Some(memory_type) = options.dialect.get_memory_type() {
+            options.memory_type = memory_type;
+        }
+        let wide_cells = options.memory_type == MemoryUnitType::Int32Bit;
+        options.input_mode = input_mode.unwrap_or(if wide_cells {
+            InputMode::Codepoints
+        } else {
+            InputMode::Bytes
+        });
+        options.output_encoding = output_encoding.unwrap_or(if wide_cells {
+            OutputEncoding::Utf8
+        } else {
+            OutputEncoding::Bytes
+        });
+        if options.max_bytes.is_none() {
+            options.max_cells = options.max_cells.or(options.dialect.get_tape_length());
+        }
+
+        if options.debug && options.file.is_none() {
+            return Err(String::from("'--debug' needs a file to run"));
+        }
+        if options.debug && options.exit_code {
+            return Err(String::from(
+                "'--exit-code' and '--exit-cell' can't be used with '--debug'",
+            ));
+        }
+        if options.split_input && !options.dialect.can_split_input() {
+            return Err(format!(
+                "'--split-input' can't be used with {}, '!' is one of its commands",
+                options.dialect
+            ));
+        }
+        if input_options.len() > 1 {
+            return Err(format!(
+                "only one input can be given, not '{}'",
+                input_options.join("' and '")
+            ));
+        }
+        if options.translate.is_some() && options.file.is_none() {
+            return Err(String::from("'--translate' needs a file to translate"));
+        }
+        // these act on a program that ran to its end, which the shell and
+        // translating never have
+        let run_options = [
+            (options.exit_code, "'--exit-code' and '--exit-cell'"),
+            (options.profile, "'--profile'"),
+            (options.dump_tape, "'--dump-tape' and '--dump-range'"),
+        ];
+        if let Some((_, name)) = run_options.iter().find(|(used, _)| *used) {
+            if options.file.is_none() {
+                return Err(format!("{} can't be used without a file to run", name));
+            }
+            if options.translate.is_some() {
+                return Err(format!("{} can't be used with '--translate'", name));
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// parses bytes written as pairs of hex digits, whitespace between the
+    /// bytes is ignored
+    fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+        let digits: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+        // from_str_radix would also take a sign
+        if !digits.len().is_multiple_of(2) || !digits.iter().all(char::is_ascii_hexdigit) {
+            return None;
+        }
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+            .collect()
+    }
+
+    /// returns the value following an option
+    fn get_value<I: Iterator<Item = String>>(option: &str, args: &mut I) -> Result<String, String> {
+        args.next()
+            .ok_or_else(|| format!("'{}' expects a value", option))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// returns the options parsed from the arguments
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn only_one_input_is_taken() {
+        assert_eq!(
+            parse(&["--input-hex", "41"]).unwrap().input,
+            Some(vec![0x41])
+        );
+        assert!(parse(&["--input", "abc", "--input-hex", "41"]).is_err());
+        assert!(parse(&["--input-hex", "41", "--input", "abc"]).is_err());
+        assert!(parse(&["--input", "abc", "--input-file", "in.txt"]).is_err());
+        assert!(parse(&["--input-file", "in.txt", "--input-hex", "41"]).is_err());
+    }
+
+    #[test]
+    fn hex_bytes_are_parsed() {
+        assert_eq!(Options::parse_hex("48 69 0a"), Some(vec![0x48, 0x69, 0x0a]));
+        assert_eq!(Options::parse_hex("ff00FF"), Some(vec![0xff, 0x00, 0xff]));
+        assert_eq!(Options::parse_hex(""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn invalid_hex_is_rejected() {
+        assert_eq!(Options::parse_hex("4"), None);
+        assert_eq!(Options::parse_hex("4 8 6"), None);
+        assert_eq!(Options::parse_hex("zz"), None);
+        assert_eq!(Options::parse_hex("+1"), None);
+    }
+
+    #[test]
+    fn run_options_need_a_program_to_run() {
+        assert!(parse(&["--exit-code", "main.bf"]).is_ok());
+        assert!(parse(&["--exit-code"]).is_err());
+        assert!(parse(&["--profile"]).is_err());
+        assert!(parse(&["--dump-range", "0..4"]).is_err());
+        assert!(parse(&["--translate", "ook", "--exit-cell", "1", "main.bf"]).is_err());
+        assert!(parse(&["--translate", "ook", "--dump-tape", "main.bf"]).is_err());
+    }
+}
@@ -1,10 +1,43 @@
 use crate::bracket_pair::BracketPair;
-use crate::enums::{BrainfuckCommand, Command, ShellCommand};
+use crate::enums::{
+    BitCommand, BrainfuckCommand, Command, Dialect, ExtendedCommand, ProcedureCommand, StackCommand,
+};
+use crate::optimizer;
+use crate::span::Span;
+use crate::token_map::TokenMap;
+
+/// commands of a source with how well their brackets match
+struct Tokens {
+    commands: Vec<Command>,
+    spans: Vec<Span>,
+    /// how many more opening than closing brackets, procedure definitions and
+    /// brackets of the second tape there are
+    open_counts: [i64; 3],
+    /// whether a closing bracket came before its opening one
+    unmatched: bool,
+}
 
 /// parser for brainfuck commands
+#[derive(Clone)]
 pub struct Parser {
     commands: Vec<Command>,
     bracket_pairs: Vec<BracketPair>,
+    /// where every command is in the source
+    spans: Vec<Span>,
+    /// character that is parsed as a breakpoint, if any
+    breakpoint_char: Option<char>,
+    /// id of the source that is parsed, it ends up in every span
+    file_id: usize,
+    /// language the source is written in
+    dialect: Dialect,
+    /// what comes after the end of the program, it is put on the tape
+    data: Option<String>,
+    /// tokens the commands are written as, if they are not brainfuck characters
+    token_map: Option<TokenMap>,
+    /// whether the source is split at the first '!' into code and its input
+    split_input: bool,
+    /// what comes after the first '!' if the source is split, the input of ','
+    input: Option<String>,
 }
 
 impl Parser {
@@ -13,73 +46,169 @@ impl Parser {
         Self {
             commands: Vec::new(),
             bracket_pairs: Vec::new(),
+            spans: Vec::new(),
+            breakpoint_char: None,
+            file_id: 0,
+            dialect: Dialect::Brainfuck,
+            data: None,
+            token_map: None,
+            split_input: false,
+            input: None,
         }
     }
 
     /// returns an Option of parser that tried to parse the provided string
     /// Option is returned because the parse could fail
-    pub fn from_string(input_string: &String) -> Option<Self> {
-        match Self::parse_commands(input_string) {
-            Some(commands) => {
-                let bracket_pairs = Self::find_bracket_pairs(&commands);
-                return Some(Self {
-                    commands,
-                    bracket_pairs,
-                });
-            }
-            None => return None,
-        }
+    pub fn from_string(input_string: &str) -> Option<Self> {
+        let mut parser = Self::new();
+        parser.parse_string(input_string)?;
+        Some(parser)
+    }
+
+    /// sets the character that is parsed as a breakpoint, None disables breakpoints
+    pub fn set_breakpoint_char(&mut self, breakpoint_char: Option<char>) {
+        self.breakpoint_char = breakpoint_char;
+    }
+
+    /// sets the id of the source that is parsed next
+    pub fn set_file_id(&mut self, file_id: usize) {
+        self.file_id = file_id;
+    }
+
+    /// sets the language of the source that is parsed next
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    /// sets the tokens the commands of the source are written as, None means
+    /// they are written as brainfuck characters
+    pub fn set_token_map(&mut self, token_map: Option<TokenMap>) {
+        self.token_map = token_map;
+    }
+
+    /// sets whether the source is split at the first '!', the text after it is
+    /// the input of the program and not parsed
+    pub fn set_split_input(&mut self, split_input: bool) {
+        self.split_input = split_input;
     }
 
     /// function to parse a string and turn it into a vector of commands
-    /// returns a Result type because the parsing could fail
-    pub fn parse_string(&mut self, input_string: &String) -> Option<bool> {
-        match Self::parse_commands(input_string) {
-            Some(commands) => {
-                self.commands = commands;
+    /// returns None if the parsing failed
+    pub fn parse_string(&mut self, input_string: &str) -> Option<bool> {
+        let (code, input) = self.split_code(input_string);
+        match self.parse_commands(code) {
+            Some((commands, spans)) => {
+                self.input = input.map(String::from);
+                self.data = Self::find_data(&commands, &spans, code);
                 self.bracket_pairs = Self::find_bracket_pairs(&commands);
-                return Some(true);
+                self.commands = commands;
+                self.spans = spans;
+                Some(true)
             }
             None => None,
         }
     }
 
+    /// replaces runs of commands with optimized commands that do the same
+    pub fn optimize(&mut self) {
+        let (commands, spans) = optimizer::optimize(&self.commands, &self.spans);
+        self.bracket_pairs = Self::find_bracket_pairs(&commands);
+        self.commands = commands;
+        self.spans = spans;
+    }
+
+    /// returns how many more opening than closing brackets the commands of
+    /// the string have, it is negative if there are more closing brackets or
+    /// one comes before its opening bracket
+    pub fn count_open_brackets(&self, string: &str) -> i64 {
+        let (code, _) = self.split_code(string);
+        let tokens = self.tokenize(code);
+        if tokens.unmatched {
+            return -1;
+        }
+        match tokens.open_counts.iter().min() {
+            Some(&count) if count < 0 => count,
+            _ => tokens.open_counts.iter().sum(),
+        }
+    }
+
     /// returns vector of commands
     pub fn get_commands(&self) -> Vec<Command> {
-        self.commands
+        self.commands.clone()
+    }
+
+    /// returns the data that comes after the end of the program, if there is any
+    pub fn get_data(&self) -> Option<String> {
+        self.data.clone()
+    }
+
+    /// returns the input that comes after the first '!', if the source is split
+    /// and has one
+    pub fn get_input(&self) -> Option<String> {
+        self.input.clone()
+    }
+
+    /// returns the code and the input after the first '!' if the source is split
+    fn split_code<'a>(&self, string: &'a str) -> (&'a str, Option<&'a str>) {
+        match string.split_once('!') {
+            Some((code, input)) if self.split_input => (code, Some(input)),
+            _ => (string, None),
+        }
     }
 
     /// returns vector of bracket pairs
     pub fn get_bracket_pairs(&self) -> Vec<BracketPair> {
-        self.bracket_pairs
+        self.bracket_pairs.clone()
+    }
+
+    /// returns where every command is in the source
+    pub fn get_spans(&self) -> Vec<Span> {
+        self.spans.clone()
     }
 
     /// function that finds all the bracket pairs in the command vector
-    // TODO: come up with a good way to couple the brackets together that does not suck
-    // it should also work with the standard enum of BrainfuckCommands
-    fn find_bracket_pairs(commands: &Vec<Command>) -> Vec<BracketPair> {
+    fn find_bracket_pairs(commands: &[Command]) -> Vec<BracketPair> {
         let mut pairs: Vec<(usize, usize)> = Vec::new();
         let mut bracket_order: Vec<usize> = Vec::new();
+        // procedure definitions and the brackets of the second tape are
+        // paired like brackets, but on their own
+        let mut procedure_order: Vec<usize> = Vec::new();
+        let mut tape_bracket_order: Vec<usize> = Vec::new();
 
         // iterate through all elements with indices
         for (index, element) in commands.iter().enumerate() {
             match element {
-                Command::Brainfuck(c) => match c {
-                    BrainfuckCommand::OpenBracket => {
-                        // add open bracket to list
-                        pairs.push((index, 0));
-                        // add last index of pair_vec to vector
-                        // keep track of last opened bracket
-                        bracket_order.push(pairs.len() - 1);
+                Command::Brainfuck(BrainfuckCommand::OpenBracket) => {
+                    // add open bracket to list
+                    pairs.push((index, 0));
+                    // add last index of pair_vec to vector
+                    // keep track of last opened bracket
+                    bracket_order.push(pairs.len() - 1);
+                }
+                Command::Brainfuck(BrainfuckCommand::ClosedBracket) => {
+                    // set second bracket index, at point of the last bracket
+                    pairs[bracket_order[bracket_order.len() - 1]].1 = index;
+                    // remove last opened bracket
+                    bracket_order.remove(bracket_order.len() - 1);
+                }
+                Command::Procedure(ProcedureCommand::StartDefinition) => {
+                    pairs.push((index, 0));
+                    procedure_order.push(pairs.len() - 1);
+                }
+                Command::Procedure(ProcedureCommand::EndDefinition) => {
+                    if let Some(pair) = procedure_order.pop() {
+                        pairs[pair].1 = index;
                     }
-                    BrainfuckCommand::ClosedBracket => {
-                        // set second bracket index, at point of the last bracket
-                        pairs[bracket_order[bracket_order.len() - 1]].1 = index;
-                        // remove last opened bracket
-                        bracket_order.remove(bracket_order.len() - 1);
+                }
+                Command::SecondTape(BrainfuckCommand::OpenBracket) => {
+                    pairs.push((index, 0));
+                    tape_bracket_order.push(pairs.len() - 1);
+                }
+                Command::SecondTape(BrainfuckCommand::ClosedBracket) => {
+                    if let Some(pair) = tape_bracket_order.pop() {
+                        pairs[pair].1 = index;
                     }
-                    _ => (),
-                },
+                }
                 _ => (),
             }
         }
@@ -93,52 +222,240 @@ impl Parser {
         bracket_pairs
     }
 
-    /// function that turns a string into a vector of commands
-    fn parse_commands(string: &String) -> Option<Vec<Command>> {
+    /// returns everything after the '@' that ends an extended program
+    fn find_data(commands: &[Command], spans: &[Span], string: &str) -> Option<String> {
+        match (commands.last(), spans.last()) {
+            (Some(Command::Extended(ExtendedCommand::EndProgram)), Some(span)) => {
+                Some(string[span.get_offset() + span.get_length()..].to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// returns the extended command for the character, if the dialect has one
+    fn parse_extended_command(dialect: Dialect, c: char) -> Option<ExtendedCommand> {
+        if dialect != Dialect::ExtendedTypeOne {
+            return None;
+        }
+        match c {
+            '@' => Some(ExtendedCommand::EndProgram),
+            '$' => Some(ExtendedCommand::StoreData),
+            '!' => Some(ExtendedCommand::LoadData),
+            '}' => Some(ExtendedCommand::ShiftRight),
+            '{' => Some(ExtendedCommand::ShiftLeft),
+            '~' => Some(ExtendedCommand::Not),
+            '^' => Some(ExtendedCommand::Xor),
+            '&' => Some(ExtendedCommand::And),
+            '|' => Some(ExtendedCommand::Or),
+            _ => None,
+        }
+    }
+
+    /// function that turns a string into a vector of commands and their spans
+    /// returns None if the brackets do not match
+    fn parse_commands(&self, string: &str) -> Option<(Vec<Command>, Vec<Span>)> {
+        let tokens = self.tokenize(string);
+        if tokens.unmatched || tokens.open_counts != [0; 3] {
+            None
+        } else {
+            Some((tokens.commands, tokens.spans))
+        }
+    }
+
+    /// turns a string into commands and their spans and counts their brackets
+    fn tokenize(&self, string: &str) -> Tokens {
+        let mut unmatched = false;
         let mut open_bracket_count = 0;
         let mut closed_bracket_count = 0;
+        let mut open_procedure_count = 0;
+        let mut closed_procedure_count = 0;
+        let mut open_tape_bracket_count = 0;
+        let mut closed_tape_bracket_count = 0;
 
-        let mut next_is_command = false;
         let mut commands: Vec<Command> = Vec::new();
+        let mut spans: Vec<Span> = Vec::new();
 
-        for c in string.chars() {
-            match c {
-                '>' => commands.push(Command::Brainfuck(BrainfuckCommand::PointerIncrement)),
-                '<' => commands.push(Command::Brainfuck(BrainfuckCommand::PointerDecrement)),
-                '+' => commands.push(Command::Brainfuck(BrainfuckCommand::DataIncrement)),
-                '-' => commands.push(Command::Brainfuck(BrainfuckCommand::DataDecrement)),
-                '.' => commands.push(Command::Brainfuck(BrainfuckCommand::ReturnDataAtPointer)),
-                ',' => commands.push(Command::Brainfuck(BrainfuckCommand::WriteDataToPointer)),
+        let mut line = 1;
+        let mut column = 0;
+
+        let mut offset = 0;
+        while let Some(first) = string[offset..].chars().next() {
+            // with a token map, only tokens are commands and everything else
+            // apart from breakpoints is a comment
+            let (c, length) = match &self.token_map {
+                Some(token_map) => match token_map.match_token(&string[offset..]) {
+                    Some((c, length)) => (Some(c), length),
+                    None if Some(first) == self.breakpoint_char => (Some(first), first.len_utf8()),
+                    None => (None, first.len_utf8()),
+                },
+                None => (Some(first), first.len_utf8()),
+            };
+            let span = Span::new(self.file_id, offset, length, line, column + 1);
+
+            // the line and column after the token
+            for c in string[offset..offset + length].chars() {
+                if c == '\n' {
+                    line += 1;
+                    column = 0;
+                } else {
+                    column += 1;
+                }
+            }
+            offset += length;
+
+            let c = match c {
+                Some(c) => c,
+                None => continue,
+            };
+            let command = match c {
+                // the bit dialects leave out some brainfuck commands and
+                // give ',' a meaning of its own
+                '-' | '.' if self.dialect == Dialect::Boolfuck => None,
+                ',' if self.dialect == Dialect::Boolfuck => Some(Command::Bit(BitCommand::ReadBit)),
+                ';' if self.dialect == Dialect::Boolfuck => {
+                    Some(Command::Bit(BitCommand::WriteBit))
+                }
+                '+' | '-' | '.' | ',' if self.dialect == Dialect::Smallfuck => None,
+                '*' if self.dialect == Dialect::Smallfuck => Some(Command::Bit(BitCommand::Flip)),
+                '>' => Some(Command::Brainfuck(BrainfuckCommand::PointerIncrement)),
+                '<' => Some(Command::Brainfuck(BrainfuckCommand::PointerDecrement)),
+                '+' => Some(Command::Brainfuck(BrainfuckCommand::DataIncrement)),
+                '-' => Some(Command::Brainfuck(BrainfuckCommand::DataDecrement)),
+                '.' => Some(Command::Brainfuck(BrainfuckCommand::ReturnDataAtPointer)),
+                ',' => Some(Command::Brainfuck(BrainfuckCommand::WriteDataToPointer)),
                 '[' => {
-                    commands.push(Command::Brainfuck(BrainfuckCommand::OpenBracket));
                     open_bracket_count += 1;
+                    Some(Command::Brainfuck(BrainfuckCommand::OpenBracket))
                 }
                 ']' => {
-                    commands.push(Command::Brainfuck(BrainfuckCommand::ClosedBracket));
                     closed_bracket_count += 1;
+                    // a closing bracket without an opening one can never be matched
+                    unmatched |= closed_bracket_count > open_bracket_count;
+                    Some(Command::Brainfuck(BrainfuckCommand::ClosedBracket))
                 }
-                ':' => {
-                    next_is_command = true;
-                    continue;
+                _ if Some(c) == self.breakpoint_char => Some(Command::Breakpoint),
+                '(' if self.dialect == Dialect::Pbrain => {
+                    open_procedure_count += 1;
+                    Some(Command::Procedure(ProcedureCommand::StartDefinition))
                 }
-                _ => (),
+                ')' if self.dialect == Dialect::Pbrain => {
+                    closed_procedure_count += 1;
+                    unmatched |= closed_procedure_count > open_procedure_count;
+                    Some(Command::Procedure(ProcedureCommand::EndDefinition))
+                }
+                ':' if self.dialect == Dialect::Pbrain => {
+                    Some(Command::Procedure(ProcedureCommand::Call))
+                }
+                'Y' if self.dialect == Dialect::Brainfork => Some(Command::Fork),
+                '$' if self.dialect == Dialect::Stack => Some(Command::Stack(StackCommand::Push)),
+                '!' if self.dialect == Dialect::Stack => Some(Command::Stack(StackCommand::Pop)),
+                '^' if self.dialect == Dialect::Doublefuck => {
+                    Some(Command::SecondTape(BrainfuckCommand::PointerIncrement))
+                }
+                'v' if self.dialect == Dialect::Doublefuck => {
+                    Some(Command::SecondTape(BrainfuckCommand::PointerDecrement))
+                }
+                '/' if self.dialect == Dialect::Doublefuck => {
+                    Some(Command::SecondTape(BrainfuckCommand::DataIncrement))
+                }
+                '\\' if self.dialect == Dialect::Doublefuck => {
+                    Some(Command::SecondTape(BrainfuckCommand::DataDecrement))
+                }
+                ':' if self.dialect == Dialect::Doublefuck => {
+                    Some(Command::SecondTape(BrainfuckCommand::ReturnDataAtPointer))
+                }
+                ';' if self.dialect == Dialect::Doublefuck => {
+                    Some(Command::SecondTape(BrainfuckCommand::WriteDataToPointer))
+                }
+                '{' if self.dialect == Dialect::Doublefuck => {
+                    open_tape_bracket_count += 1;
+                    Some(Command::SecondTape(BrainfuckCommand::OpenBracket))
+                }
+                '}' if self.dialect == Dialect::Doublefuck => {
+                    closed_tape_bracket_count += 1;
+                    unmatched |= closed_tape_bracket_count > open_tape_bracket_count;
+                    Some(Command::SecondTape(BrainfuckCommand::ClosedBracket))
+                }
+                _ => Self::parse_extended_command(self.dialect, c).map(Command::Extended),
             };
 
-            if next_is_command {
-                next_is_command = false;
-                match c {
-                    'r' => commands.push(Command::Shell(ShellCommand::ResetValues)),
-                    'p' => commands.push(Command::Shell(ShellCommand::PrintString)),
-                    'q' => commands.push(Command::Shell(ShellCommand::QuitProgram)),
-                    _ => (),
-                };
+            // the rest of the source is data, not commands, if the program
+            // ends outside of all loops, inside one '@' only stops it
+            let ends_program = command == Some(Command::Extended(ExtendedCommand::EndProgram))
+                && open_bracket_count == closed_bracket_count
+                && open_procedure_count == closed_procedure_count
+                && open_tape_bracket_count == closed_tape_bracket_count;
+
+            if let Some(command) = command {
+                commands.push(command);
+                spans.push(span);
+            }
+
+            if ends_program {
+                break;
             }
         }
 
-        if open_bracket_count != closed_bracket_count {
-            None
-        } else {
-            Some(commands)
+        Tokens {
+            commands,
+            spans,
+            open_counts: [
+                open_bracket_count - closed_bracket_count,
+                open_procedure_count - closed_procedure_count,
+                open_tape_bracket_count - closed_tape_bracket_count,
+            ],
+            unmatched,
         }
     }
 }
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// returns a parser for the dialect
+    fn parser(dialect: Dialect) -> Parser {
+        let mut parser = Parser::new();
+        parser.set_dialect(dialect);
+        parser
+    }
+
+    #[test]
+    fn open_brackets_are_counted() {
+        let parser = parser(Dialect::Brainfuck);
+        assert_eq!(parser.count_open_brackets("+[>[-]"), 1);
+        assert_eq!(parser.count_open_brackets("+[>[-]]"), 0);
+        assert_eq!(parser.count_open_brackets("]"), -1);
+        assert_eq!(parser.count_open_brackets("][["), -1);
+    }
+
+    #[test]
+    fn open_brackets_of_dialects_are_counted() {
+        assert_eq!(parser(Dialect::Pbrain).count_open_brackets("(+++"), 1);
+        assert_eq!(parser(Dialect::Brainfuck).count_open_brackets("(+++"), 0);
+        assert_eq!(parser(Dialect::Doublefuck).count_open_brackets("{[/"), 2);
+    }
+
+    #[test]
+    fn open_brackets_of_tokens_are_counted() {
+        let mut parser = parser(Dialect::Brainfuck);
+        parser.set_token_map(TokenMap::from_name("ook"));
+        assert_eq!(parser.count_open_brackets("Ook. Ook. Ook! Ook?"), 1);
+        assert_eq!(parser.count_open_brackets("Ook! Ook? Ook? Ook!"), 0);
+        assert_eq!(parser.count_open_brackets("[[["), 0);
+    }
+
+    #[test]
+    fn data_starts_at_an_end_outside_of_loops() {
+        let mut parser = parser(Dialect::ExtendedTypeOne);
+        assert!(parser.parse_string("+[>@]<@data").is_some());
+        assert_eq!(parser.get_commands().len(), 7);
+        assert_eq!(parser.get_data(), Some(String::from("data")));
+    }
+}
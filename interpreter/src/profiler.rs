@@ -0,0 +1,144 @@
+use crate::enums::BrainfuckCommand::{ClosedBracket, OpenBracket};
+use crate::enums::Command;
+use crate::executor::Executor;
+
+/// number of loops shown in the hot spot report
+const HOT_LOOP_COUNT: usize = 10;
+
+/// counts how often every command is run and how often every loop repeats
+pub struct Profiler {
+    /// number of times the command at each index was run
+    counts: Vec<u64>,
+    /// number of times the loop starting at each index ran its body
+    iterations: Vec<u64>,
+}
+
+impl Profiler {
+    /// returns a profiler for a program with the number of commands
+    pub fn new(command_count: usize) -> Self {
+        Self {
+            counts: vec![0; command_count],
+            iterations: vec![0; command_count],
+        }
+    }
+
+    /// counts a run of the command at the index
+    pub fn record(&mut self, index: usize) {
+        if let Some(count) = self.counts.get_mut(index) {
+            *count += 1;
+        }
+    }
+
+    /// counts an iteration of the loop whose opening bracket is at the index
+    pub fn record_iteration(&mut self, index: usize) {
+        if let Some(count) = self.iterations.get_mut(index) {
+            *count += 1;
+        }
+    }
+
+    /// returns a report of the loops that ran the most commands, followed by
+    /// a listing of all commands with their counts
+    pub fn format_report(&self, executor: &Executor) -> String {
+        let mut report = String::new();
+        let total: u64 = self.counts.iter().sum();
+        report.push_str(&format!("{} commands run\n\n", total));
+
+        // every loop with the number of commands run inside of it
+        let mut loops: Vec<(usize, usize, u64)> = executor
+            .get_bracket_pairs()
+            .iter()
+            // procedure definitions are paired like loops, but are not loops
+            .filter(|pair| {
+                executor.get_command(pair.get_first_bracket_index())
+                    == Some(Command::Brainfuck(OpenBracket))
+            })
+            .map(|pair| {
+                let first = pair.get_first_bracket_index();
+                let second = pair.get_second_bracket_index();
+                (first, second, self.counts[first..=second].iter().sum())
+            })
+            .collect();
+        loops.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+
+        report.push_str("hottest loops:\n");
+        report.push_str("  rank  span                 iterations    commands run\n");
+        for (rank, (first, second, commands_run)) in loops.iter().take(HOT_LOOP_COUNT).enumerate() {
+            let span = match (executor.get_span(*first), executor.get_span(*second)) {
+                (Some(start), Some(end)) => format!("{}-{}", start, end),
+                _ => format!("{}-{}", first, second),
+            };
+            report.push_str(&format!(
+                "  {:>4}  {:<19} {:>11} {:>15}\n",
+                rank + 1,
+                span,
+                self.iterations[*first],
+                commands_run
+            ));
+        }
+        if loops.is_empty() {
+            report.push_str("  no loops\n");
+        }
+
+        report.push_str("\nannotated source:\n");
+        report.push_str(&self.format_listing(executor));
+        report
+    }
+
+    /// returns one row per run of commands on the same line that were run
+    /// equally often, indented by how deep they are nested in loops
+    fn format_listing(&self, executor: &Executor) -> String {
+        let mut listing = String::new();
+        let mut depth: usize = 0;
+        let mut index = 0;
+
+        while index < self.counts.len() {
+            let count = self.counts[index];
+            let span = executor.get_span(index).unwrap_or_default();
+
+            // closing brackets are shown at the depth of their opening bracket
+            if executor.get_command(index) == Some(Command::Brainfuck(ClosedBracket)) {
+                depth = depth.saturating_sub(1);
+            }
+
+            let mut commands = String::new();
+            let mut opened = false;
+            while index < self.counts.len()
+                && self.counts[index] == count
+                && executor.get_span(index).map(|s| s.get_line()) == Some(span.get_line())
+            {
+                match executor.get_command(index) {
+                    Some(Command::Brainfuck(OpenBracket)) => {
+                        commands.push('[');
+                        opened = true;
+                        index += 1;
+                        // every bracket ends the row, so that the
+                        // indentation follows the loops
+                        break;
+                    }
+                    Some(Command::Brainfuck(ClosedBracket)) if !commands.is_empty() => break,
+                    Some(Command::Brainfuck(ClosedBracket)) => {
+                        commands.push(']');
+                        index += 1;
+                        break;
+                    }
+                    Some(command) => commands.push_str(&command.to_string()),
+                    None => (),
+                }
+                index += 1;
+            }
+
+            listing.push_str(&format!(
+                "{:>12}  {:>5}:{:<4} {}{}\n",
+                count,
+                span.get_line(),
+                span.get_column(),
+                "  ".repeat(depth),
+                commands
+            ));
+            if opened {
+                depth += 1;
+            }
+        }
+        listing
+    }
+}
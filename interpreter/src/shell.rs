@@ -0,0 +1,153 @@
+use crate::enums::{EofBehavior, MemoryUnitType, ShellCommand};
+
+/// list of all shell commands, shown by ':help'
+const SHELL_HELP: &str = "\
+shell commands, they have to be on a line of their own:
+  :help, :h             show this list
+  :r, :reset            reset the pointer and all cells, forget procedures
+  :p, :print            print everything output since the last :p
+  :mem [range], :m      show the cells in the range, like 0..16, or all of them
+  :ptr                  show the pointer and the cell it points at
+  :stack                show the values on the stack, the top one first
+  :set cell=value       set a cell on the tape to the value
+  :width 1|8|32         change the size of the cells, values are cut off to fit
+  :eof [0|-1|unchanged] set what ',' stores at the end of the input, or show it
+  :time                 turn printing how long every program ran on or off
+  :opt                  turn optimizing the following programs on or off
+  :save file            save the pointers and cells of all tapes to the file
+  :load file            restore a file made with :save, or run a program file
+  :q, :quit             quit";
+
+/// returns the list of shell commands with the prefix they start with
+pub fn format_help(prefix: char) -> String {
+    SHELL_HELP.replace(" :", &format!(" {}", prefix))
+}
+
+/// parses a line typed at the shell prompt, a shell command is the prefix and
+/// a word at the start of the line, followed by an argument for some commands
+/// returns None if the line is not a shell command but brainfuck source
+pub fn parse_line(line: &str, prefix: char) -> Option<ShellCommand> {
+    let line = line.trim().strip_prefix(prefix)?;
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim())),
+        None => (line, None),
+    };
+
+    let command = match (name, argument) {
+        ("h" | "help", None) => ShellCommand::Help,
+        ("r" | "reset", None) => ShellCommand::ResetValues,
+        ("p" | "print", None) => ShellCommand::PrintString,
+        ("m" | "mem", range) => ShellCommand::PrintMemory(range.map(String::from)),
+        ("ptr", None) => ShellCommand::PrintPointer,
+        ("stack", None) => ShellCommand::PrintStack,
+        ("set", Some(argument)) => parse_set_cell(argument, prefix),
+        ("width", Some("1")) => ShellCommand::SetWidth(MemoryUnitType::Bit),
+        ("width", Some("8")) => ShellCommand::SetWidth(MemoryUnitType::Int8Bit),
+        ("width", Some("32")) => ShellCommand::SetWidth(MemoryUnitType::Int32Bit),
+        ("eof", None) => ShellCommand::SetEof(None),
+        ("eof", Some("0")) => ShellCommand::SetEof(Some(EofBehavior::Zero)),
+        ("eof", Some("-1")) => ShellCommand::SetEof(Some(EofBehavior::MaxValue)),
+        ("eof", Some("unchanged")) => ShellCommand::SetEof(Some(EofBehavior::Unchanged)),
+        ("time", None) => ShellCommand::ToggleTime,
+        ("opt", None) => ShellCommand::ToggleOptimization,
+        ("save", Some(path)) => ShellCommand::SaveState(path.to_string()),
+        ("load", Some(path)) => ShellCommand::LoadFile(path.to_string()),
+        ("q" | "quit", None) => ShellCommand::QuitProgram,
+        ("set", None) => ShellCommand::Invalid(format!("{}set expects cell=value", prefix)),
+        ("width", _) => ShellCommand::Invalid(format!("{}width expects 1, 8 or 32", prefix)),
+        ("eof", _) => ShellCommand::Invalid(format!("{}eof expects 0, -1 or unchanged", prefix)),
+        ("save" | "load", None) => {
+            ShellCommand::Invalid(format!("{}{} expects a file name", prefix, name))
+        }
+        (
+            "h" | "help" | "r" | "reset" | "p" | "print" | "ptr" | "stack" | "time" | "opt" | "q"
+            | "quit",
+            _,
+        ) => ShellCommand::Invalid(format!("{}{} takes no argument", prefix, name)),
+        _ => ShellCommand::Invalid(format!(
+            "unknown shell command {}{}, try {}help",
+            prefix, name, prefix
+        )),
+    };
+    Some(command)
+}
+
+/// parses the argument of ":set", a cell index and a value like "3=65"
+fn parse_set_cell(argument: &str, prefix: char) -> ShellCommand {
+    let cell = argument
+        .split_once('=')
+        .and_then(|(index, value)| Some((index.trim().parse().ok()?, value.trim().parse().ok()?)));
+    match cell {
+        Some((index, value)) => ShellCommand::SetCell { index, value },
+        None => ShellCommand::Invalid(format!(
+            "{}set expects cell=value, not '{}'",
+            prefix, argument
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(parse_line(":help", ':'), Some(ShellCommand::Help));
+        assert_eq!(parse_line("  :q  ", ':'), Some(ShellCommand::QuitProgram));
+        assert_eq!(
+            parse_line(":m 0..16", ':'),
+            Some(ShellCommand::PrintMemory(Some(String::from("0..16"))))
+        );
+        assert_eq!(
+            parse_line(":mem", ':'),
+            Some(ShellCommand::PrintMemory(None))
+        );
+        assert_eq!(
+            parse_line(":set 3 = 65", ':'),
+            Some(ShellCommand::SetCell {
+                index: 3,
+                value: 65
+            })
+        );
+        assert_eq!(
+            parse_line(":width 32", ':'),
+            Some(ShellCommand::SetWidth(MemoryUnitType::Int32Bit))
+        );
+        assert_eq!(
+            parse_line(":eof unchanged", ':'),
+            Some(ShellCommand::SetEof(Some(EofBehavior::Unchanged)))
+        );
+        assert_eq!(
+            parse_line(":save a b", ':'),
+            Some(ShellCommand::SaveState(String::from("a b")))
+        );
+    }
+
+    #[test]
+    fn source_is_not_a_command() {
+        assert_eq!(parse_line("+[>.]", ':'), None);
+        assert_eq!(parse_line(":q", '#'), None);
+        assert_eq!(parse_line("#q", '#'), Some(ShellCommand::QuitProgram));
+    }
+
+    #[test]
+    fn invalid_commands_are_reported() {
+        let invalid = [
+            ":set",
+            ":set 3",
+            ":set x=1",
+            ":width 16",
+            ":eof 1",
+            ":load",
+            ":q now",
+            ":unknown",
+        ];
+        for line in invalid {
+            assert!(
+                matches!(parse_line(line, ':'), Some(ShellCommand::Invalid(_))),
+                "{}",
+                line
+            );
+        }
+    }
+}
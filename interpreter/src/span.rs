@@ -0,0 +1,68 @@
+use std::fmt;
+
+/// struct representing where a command is in the source it was parsed from
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    /// id of the source, given out by the executor the source is run on
+    file_id: usize,
+    /// byte offset of the first character
+    offset: usize,
+    /// number of bytes, more than one character for optimized commands
+    length: usize,
+    /// line of the first character, starting at 1
+    line: usize,
+    /// column of the first character, starting at 1
+    column: usize,
+}
+
+impl Span {
+    /// create a span from where it starts and how many bytes it covers
+    pub fn new(file_id: usize, offset: usize, length: usize, line: usize, column: usize) -> Self {
+        Span {
+            file_id,
+            offset,
+            length,
+            line,
+            column,
+        }
+    }
+
+    /// returns a span from the start of this one to the end of the other one
+    pub fn merge(&self, other: &Span) -> Self {
+        Span {
+            length: other.offset + other.length - self.offset,
+            ..*self
+        }
+    }
+
+    /// returns the id of the source
+    pub fn get_file_id(&self) -> usize {
+        self.file_id
+    }
+
+    /// returns the byte offset of the first character
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// returns the number of bytes
+    pub fn get_length(&self) -> usize {
+        self.length
+    }
+
+    /// returns the line of the first character
+    pub fn get_line(&self) -> usize {
+        self.line
+    }
+
+    /// returns the column of the first character
+    pub fn get_column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
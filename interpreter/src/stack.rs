@@ -0,0 +1,125 @@
+/// struct storing the values the stack dialect pushes cells onto
+#[derive(Clone, Debug, Default)]
+pub struct Stack {
+    values: Vec<u32>,
+    /// maximum number of values the stack can hold
+    max_length: Option<usize>,
+}
+
+impl Stack {
+    /// returns an empty Stack without a size limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sets the maximum number of values the stack can hold, None removes the limit
+    pub fn set_max_length(&mut self, max_length: Option<usize>) {
+        self.max_length = max_length;
+    }
+
+    /// returns the maximum number of values the stack can hold
+    pub fn get_max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    /// puts the value on top of the stack
+    /// returns None without pushing if the stack is full
+    pub fn push(&mut self, value: u32) -> Option<()> {
+        if self.max_length.is_some_and(|max| self.values.len() >= max) {
+            return None;
+        }
+        self.values.push(value);
+        Some(())
+    }
+
+    /// removes the value on top of the stack and returns it, None if the
+    /// stack is empty
+    pub fn pop(&mut self) -> Option<u32> {
+        self.values.pop()
+    }
+
+    /// returns the value on top of the stack
+    pub fn peek(&self) -> Option<u32> {
+        self.values.last().copied()
+    }
+
+    /// returns all values, the bottom one first
+    pub fn get_values(&self) -> &[u32] {
+        &self.values
+    }
+
+    /// returns the number of values on the stack
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// checks whether there are no values on the stack
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// removes all values
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// restores the stack to the length it had when the top value was the
+    /// given one, after a single push or pop
+    pub fn undo(&mut self, length: usize, top: Option<u32>) {
+        self.values.truncate(length);
+        if self.values.len() < length {
+            self.values.extend(top);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_stack_rejects_pushes() {
+        let mut stack = Stack::new();
+        stack.set_max_length(Some(2));
+        assert_eq!(stack.push(1), Some(()));
+        assert_eq!(stack.push(2), Some(()));
+        assert_eq!(stack.push(3), None);
+        assert_eq!(stack.get_values(), &[1, 2]);
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.peek(), Some(1));
+    }
+
+    #[test]
+    fn undo_reverts_a_push() {
+        let mut stack = Stack::new();
+        stack.push(1);
+        let (length, top) = (stack.len(), stack.peek());
+        stack.push(2);
+        stack.undo(length, top);
+        assert_eq!(stack.get_values(), &[1]);
+    }
+
+    #[test]
+    fn undo_reverts_a_pop() {
+        let mut stack = Stack::new();
+        stack.push(1);
+        stack.push(2);
+        let (length, top) = (stack.len(), stack.peek());
+        stack.pop();
+        stack.undo(length, top);
+        assert_eq!(stack.get_values(), &[1, 2]);
+    }
+
+    #[test]
+    fn undo_without_change_keeps_the_stack() {
+        let mut stack = Stack::new();
+        stack.push(1);
+        stack.undo(1, Some(1));
+        assert_eq!(stack.get_values(), &[1]);
+
+        let mut empty = Stack::new();
+        empty.pop();
+        empty.undo(0, None);
+        assert!(empty.is_empty());
+    }
+}
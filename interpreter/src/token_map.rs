@@ -0,0 +1,356 @@
+use std::fs;
+
+use crate::enums::Command;
+
+/// characters a token can stand for, the commands of brainfuck and all its
+/// dialects
+const COMMAND_CHARS: &str = "><+-.,[]@$!}{~^&|():Y*;v/\\";
+
+/// number of characters after which a translated program is wrapped
+const LINE_WIDTH: usize = 80;
+
+/// struct mapping the tokens of a language that substitutes brainfuck
+/// commands, like Ook!, to the characters of the commands
+#[derive(Clone, Debug)]
+pub struct TokenMap {
+    /// every token with its command character, the longest tokens first so
+    /// that they are matched before tokens that are their prefix
+    tokens: Vec<(String, char)>,
+}
+
+impl TokenMap {
+    /// returns the built-in map with the name, "brainfuck", "ook", "blub"
+    /// or "alphuck"
+    pub fn from_name(name: &str) -> Option<Self> {
+        let words = |word: &str| {
+            let ook = |a: &str, b: &str| format!("{}{} {}{}", word, a, word, b);
+            vec![
+                (ook(".", "?"), '>'),
+                (ook("?", "."), '<'),
+                (ook(".", "."), '+'),
+                (ook("!", "!"), '-'),
+                (ook("!", "."), '.'),
+                (ook(".", "!"), ','),
+                (ook("!", "?"), '['),
+                (ook("?", "!"), ']'),
+            ]
+        };
+        let characters = |characters: &str| {
+            characters
+                .chars()
+                .zip("><+-.,[]".chars())
+                .map(|(token, c)| (token.to_string(), c))
+                .collect()
+        };
+
+        let tokens = match name {
+            "brainfuck" => characters("><+-.,[]"),
+            "ook" => words("Ook"),
+            "blub" => words("Blub"),
+            "alphuck" => characters("aceijops"),
+            _ => return None,
+        };
+        Some(Self::from_tokens(tokens))
+    }
+
+    /// reads a map from a JSON object or a TOML table of command characters
+    /// to tokens, like {"+": "Ook. Ook."} or "+" = "Ook. Ook."
+    /// returns an error message if the file can't be read or is invalid
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("could not read '{}': {}", path, error))?;
+        let pairs = if text.trim_start().starts_with('{') {
+            Self::parse_json(&text)
+        } else {
+            Self::parse_toml(&text)
+        }
+        .map_err(|message| format!("'{}' is not a valid token map: {}", path, message))?;
+
+        let mut tokens = Vec::new();
+        for (key, token) in pairs {
+            let mut chars = key.chars();
+            let c = match (chars.next(), chars.next()) {
+                (Some(c), None) if COMMAND_CHARS.contains(c) => c,
+                _ => return Err(format!("'{}' is not a command in '{}'", key, path)),
+            };
+            if token.trim().is_empty() || tokens.iter().any(|(other, _)| *other == token) {
+                return Err(format!(
+                    "token '{}' is empty or used twice in '{}'",
+                    token, path
+                ));
+            }
+            tokens.push((token, c));
+        }
+        Ok(Self::from_tokens(tokens))
+    }
+
+    /// returns the map of the tokens, sorted so that the longest come first
+    fn from_tokens(mut tokens: Vec<(String, char)>) -> Self {
+        tokens.sort_by_key(|(token, _)| std::cmp::Reverse(token.len()));
+        Self { tokens }
+    }
+
+    /// checks whether the character is part of any token
+    pub fn uses_char(&self, c: char) -> bool {
+        self.tokens.iter().any(|(token, _)| token.contains(c))
+    }
+
+    /// returns the command character of the token at the start of the string
+    /// and how many bytes the token takes up, whitespace in a token matches
+    /// any amount of whitespace in the string
+    pub fn match_token(&self, string: &str) -> Option<(char, usize)> {
+        self.tokens.iter().find_map(|(token, c)| {
+            let length = Self::match_length(token, string)?;
+            Some((*c, length))
+        })
+    }
+
+    /// returns how many bytes at the start of the string the token matches
+    fn match_length(token: &str, string: &str) -> Option<usize> {
+        let mut rest = string;
+        let mut token_chars = token.chars().peekable();
+        while let Some(c) = token_chars.next() {
+            if c.is_whitespace() {
+                while token_chars.peek().is_some_and(|c| c.is_whitespace()) {
+                    token_chars.next();
+                }
+                let trimmed = rest.trim_start();
+                if trimmed.len() == rest.len() {
+                    return None;
+                }
+                rest = trimmed;
+            } else {
+                rest = rest.strip_prefix(c)?;
+            }
+        }
+        Some(string.len() - rest.len())
+    }
+
+    /// returns the program written with the tokens of the map, tokens of more
+    /// than one character are separated by spaces and lines are wrapped
+    /// returns an error message if a command has no token
+    pub fn translate(&self, commands: &[Command]) -> Result<String, String> {
+        let separator = if self
+            .tokens
+            .iter()
+            .all(|(token, _)| token.chars().count() == 1)
+        {
+            ""
+        } else {
+            " "
+        };
+
+        let mut program = String::new();
+        let mut line_length = 0;
+        for command in commands {
+            let c = match command {
+                Command::Brainfuck(_)
+                | Command::Extended(_)
+                | Command::Procedure(_)
+                | Command::Bit(_)
+                | Command::Stack(_)
+                | Command::SecondTape(_)
+                | Command::Fork => command.to_string(),
+                _ => continue,
+            };
+            let token = match self.tokens.iter().find(|(_, other)| other.to_string() == c) {
+                Some((token, _)) => token,
+                None => return Err(format!("there is no token for '{}'", c)),
+            };
+
+            if line_length > 0 && line_length + separator.len() + token.len() > LINE_WIDTH {
+                program.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                program.push_str(separator);
+                line_length += separator.len();
+            }
+            program.push_str(token);
+            line_length += token.len();
+        }
+        program.push('\n');
+        Ok(program)
+    }
+
+    /// parses a JSON object of strings
+    /// returns an error message if it is invalid or has other values
+    fn parse_json(text: &str) -> Result<Vec<(String, String)>, String> {
+        let object: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(text).map_err(|error| error.to_string())?;
+        object
+            .into_iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(token) => Ok((key, token)),
+                _ => Err(format!("the token of '{}' is not a string", key)),
+            })
+            .collect()
+    }
+
+    /// parses a TOML table of strings, dotted keys and nested tables are
+    /// other values than strings, since the map is flat
+    /// returns an error message if it is invalid or has other values
+    fn parse_toml(text: &str) -> Result<Vec<(String, String)>, String> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|error: toml::de::Error| error.message().to_string())?;
+        table
+            .into_iter()
+            .map(|(key, value)| match value {
+                toml::Value::String(token) => Ok((key, token)),
+                _ => Err(format!("the token of '{}' is not a string", key)),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    /// returns the pairs as pairs of strings
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, token)| (key.to_string(), token.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn json_is_parsed() {
+        let json = r#" { "+": "Ook. Ook.", "[" : "a\"b\\c\/d\n" } "#;
+        assert_eq!(
+            TokenMap::parse_json(json),
+            Ok(pairs(&[("+", "Ook. Ook."), ("[", "a\"b\\c/d\n")]))
+        );
+        assert_eq!(TokenMap::parse_json("{}"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn json_unicode_escapes_are_parsed() {
+        let json = r#"{"+": "\u00e9\ud83d\ude00"}"#;
+        assert_eq!(
+            TokenMap::parse_json(json),
+            Ok(pairs(&[("+", "\u{e9}\u{1f600}")]))
+        );
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        let invalid = [
+            r#"{"+": "a",}"#,
+            r#"{"+" "a"}"#,
+            r#"{"+": a}"#,
+            r#"{"+": "a" "-": "b"}"#,
+            r#"{"+": "\ud83d"}"#,
+            r#"{"+": "\u+0e9"}"#,
+            r#"{"+": "\q"}"#,
+            "{\"+\": \"a\nb\"}",
+            r#"["+", "a"]"#,
+            r#"{"+": 1}"#,
+            r#"{"+": {"a": "b"}}"#,
+        ];
+        for json in invalid {
+            assert!(TokenMap::parse_json(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn toml_is_parsed() {
+        let toml = "\
+# a comment
+- = \"Ook! Ook!\"   # another one
+
+\"[\" = 'C:\\path'
+'.' = \"tab\\there\"
+";
+        assert_eq!(
+            TokenMap::parse_toml(toml),
+            Ok(pairs(&[
+                ("-", "Ook! Ook!"),
+                (".", "tab\there"),
+                ("[", "C:\\path")
+            ]))
+        );
+    }
+
+    #[test]
+    fn toml_multiline_strings_are_parsed() {
+        let toml = "\
+\"+\" = \"\"\"
+one \\
+    two\"\"\"
+\"-\" = '''
+raw \\n
+lines'''
+\">\" = \"\"\"quoted \"\"\"\"\"
+";
+        assert_eq!(
+            TokenMap::parse_toml(toml),
+            Ok(pairs(&[
+                ("+", "one two"),
+                ("-", "raw \\n\nlines"),
+                (">", "quoted \"\"")
+            ]))
+        );
+    }
+
+    #[test]
+    fn invalid_toml_is_rejected() {
+        let invalid = [
+            "[tokens]\n\"+\" = \"a\"",
+            "[ = \"a\"",
+            "\"+\" = a",
+            "\"+\" = \"a\" \"b\"",
+            "\"+\" \"a\"",
+            "\"+\" = \"a\nb\"",
+            "\"+\" = \"\"\"a",
+            "a.b = \"c\"",
+            "\"+\" = { a = \"b\" }",
+            "\"+\" = 1",
+        ];
+        for toml in invalid {
+            assert!(TokenMap::parse_toml(toml).is_err(), "{}", toml);
+        }
+    }
+
+    #[test]
+    fn tokens_are_matched() {
+        let token_map = TokenMap::from_name("ook").unwrap();
+        assert_eq!(token_map.match_token("Ook. Ook? rest"), Some(('>', 9)));
+        assert_eq!(token_map.match_token("Ook.\n\t Ook!"), Some((',', 11)));
+        assert_eq!(token_map.match_token("Ook.Ook?"), None);
+        assert_eq!(token_map.match_token("Ook. "), None);
+
+        // the longest token is matched first
+        let token_map =
+            TokenMap::from_tokens(vec![(String::from("a"), '+'), (String::from("ab"), '-')]);
+        assert_eq!(token_map.match_token("abc"), Some(('-', 2)));
+        assert_eq!(token_map.match_token("acb"), Some(('+', 1)));
+    }
+
+    #[test]
+    fn translated_programs_parse_to_the_same_commands() {
+        let source = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++,";
+        let commands = Parser::from_string(source).unwrap().get_commands();
+
+        for name in ["ook", "blub", "alphuck", "brainfuck"] {
+            let token_map = TokenMap::from_name(name).unwrap();
+            let program = token_map.translate(&commands).unwrap();
+
+            let mut parser = Parser::new();
+            parser.set_token_map(Some(token_map));
+            assert!(parser.parse_string(&program).is_some(), "{}", name);
+            assert_eq!(parser.get_commands(), commands, "{}", name);
+        }
+    }
+
+    #[test]
+    fn commands_without_tokens_are_not_translated() {
+        let mut parser = Parser::new();
+        parser.set_dialect(crate::enums::Dialect::Brainfork);
+        parser.parse_string("+Y").unwrap();
+        let token_map = TokenMap::from_name("ook").unwrap();
+        assert!(token_map.translate(&parser.get_commands()).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use crate::enums::{Command, OptimizedCommand, TraceFormat};
+use crate::span::Span;
+
+/// bytes every binary trace starts with
+const BINARY_MAGIC: &[u8] = b"BFTRACE1";
+
+/// writes a line for every command that is run to a file
+pub struct Tracer {
+    format: TraceFormat,
+    writer: BufWriter<File>,
+}
+
+impl Tracer {
+    /// creates the trace file at the path
+    pub fn create(path: &str, format: TraceFormat) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if format == TraceFormat::Binary {
+            writer.write_all(BINARY_MAGIC)?;
+        }
+        Ok(Self { format, writer })
+    }
+
+    /// records a command that was run together with the state after it
+    pub fn record(
+        &mut self,
+        index: usize,
+        command: &Command,
+        span: Span,
+        pointer: usize,
+        value: u32,
+    ) -> io::Result<()> {
+        match self.format {
+            TraceFormat::Text => writeln!(
+                self.writer,
+                "{} {} {} {} {}",
+                index, span, command, pointer, value
+            ),
+            TraceFormat::Binary => {
+                // every brainfuck command is printed as a single ascii
+                // character, optimized commands as a letter and their amount
+                match command {
+                    Command::Optimized(OptimizedCommand::AddData(amount)) => {
+                        self.writer.write_all(b"a")?;
+                        self.write_signed_number(*amount as isize)?;
+                    }
+                    Command::Optimized(OptimizedCommand::MovePointer(amount)) => {
+                        self.writer.write_all(b"m")?;
+                        self.write_signed_number(*amount)?;
+                    }
+                    Command::Optimized(OptimizedCommand::ClearData) => {
+                        self.writer.write_all(b"c")?;
+                    }
+                    command => self.writer.write_all(command.to_string().as_bytes())?,
+                }
+                for number in [
+                    index,
+                    span.get_line(),
+                    span.get_column(),
+                    pointer,
+                    value as usize,
+                ] {
+                    self.write_number(number)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// writes everything that is still buffered to the file
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// writes a signed number zigzag encoded, 0, -1, 1, -2 become 0, 1, 2, 3
+    fn write_signed_number(&mut self, number: isize) -> io::Result<()> {
+        self.write_number(((number << 1) ^ (number >> (isize::BITS - 1))) as usize)
+    }
+
+    /// writes a number as unsigned LEB128, 7 bits per byte with the highest
+    /// bit set on all but the last byte
+    fn write_number(&mut self, mut number: usize) -> io::Result<()> {
+        loop {
+            let byte = (number & 0x7f) as u8;
+            number >>= 7;
+            if number == 0 {
+                return self.writer.write_all(&[byte]);
+            }
+            self.writer.write_all(&[byte | 0x80])?;
+        }
+    }
+}